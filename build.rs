@@ -0,0 +1,73 @@
+//! Compiles `src/base.css` with Tailwind into `$OUT_DIR/static`, where it is embedded
+//! by `Asset`. The output file name carries a content hash for cache-busting and is
+//! exported to the crate as `BASE_CSS`.
+//!
+//! The Tailwind standalone binary is looked up in `$TAILWIND`, `./tailwind` (where
+//! `dev.sh` downloads it) and `tailwindcss` on `PATH`. Without it, or if it fails, the
+//! vendored `assets/base.css` is used instead so offline builds still work; refresh
+//! that copy with `./dev.sh vendor` after changing styles.
+
+use std::{
+    env, fs,
+    hash::{DefaultHasher, Hasher},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const INPUT: &str = "src/base.css";
+const VENDORED: &str = "assets/base.css";
+
+fn main() {
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed={VENDORED}");
+    println!("cargo:rerun-if-env-changed=TAILWIND");
+
+    let static_dir = PathBuf::from(env::var("OUT_DIR").unwrap()).join("static");
+    if static_dir.exists() {
+        fs::remove_dir_all(&static_dir).unwrap();
+    }
+    fs::create_dir_all(&static_dir).unwrap();
+
+    let compiled = static_dir.join("base.css.tmp");
+    let css = match tailwind() {
+        Some(bin) if run_tailwind(&bin, &compiled) => fs::read(&compiled).unwrap(),
+        found => {
+            let reason = match found {
+                Some(bin) => format!("{} failed", bin.display()),
+                None => "tailwind not found".into(),
+            };
+            println!("cargo:warning={reason}, using vendored {VENDORED}");
+            fs::read(VENDORED).unwrap()
+        }
+    };
+    let _ = fs::remove_file(&compiled);
+
+    let mut hasher = DefaultHasher::new();
+    hasher.write(&css);
+    let name = format!("base.{:08x}.css", hasher.finish() as u32);
+    fs::write(static_dir.join(&name), css).unwrap();
+    println!("cargo:rustc-env=BASE_CSS={name}");
+}
+
+fn tailwind() -> Option<PathBuf> {
+    if let Some(bin) = env::var_os("TAILWIND") {
+        return Some(bin.into());
+    }
+    if Path::new("tailwind").is_file() {
+        return Some("./tailwind".into());
+    }
+    Command::new("tailwindcss")
+        .arg("--help")
+        .output()
+        .ok()
+        .map(|_| "tailwindcss".into())
+}
+
+fn run_tailwind(bin: &Path, out: &Path) -> bool {
+    Command::new(bin)
+        .args(["-i", INPUT, "-o"])
+        .arg(out)
+        .arg("--minify")
+        .status()
+        .is_ok_and(|s| s.success())
+}
@@ -14,26 +14,26 @@ if [ ! -f "./tailwind" ]; then
   fi
 fi
 
-run_tailwind() {
-  echo "Starting Tailwind CSS"
-  ./tailwind -i ./src/base.css -o ./src/static/base.css
-}
-
+# build.rs compiles the CSS with ./tailwind during `cargo build`
 run_my_search() {
   echo "Starting my-search"
   cargo run
 }
 
-run() {
-  run_tailwind && run_my_search
+# Refresh the copy embedded when building without the Tailwind binary
+vendor_css() {
+  echo "Updating vendored CSS"
+  ./tailwind -i ./src/base.css -o ./assets/base.css --minify
 }
 
 if [ $# -eq 0 ]; then
   echo "Starting watchexec..."
   watchexec --restart --watch src ./dev.sh run
 elif [ "$1" = "run" ]; then
-  run
+  run_my_search
+elif [ "$1" = "vendor" ]; then
+  vendor_css
 else
-  echo "Usage: $0 [run]"
+  echo "Usage: $0 [run|vendor]"
   exit 1
 fi
@@ -0,0 +1,121 @@
+use axum::{
+    extract::Path,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use rust_embed::Embed;
+
+#[derive(Embed)]
+#[folder = "$OUT_DIR/static/"]
+struct Asset;
+
+/// URL prefix everything in [`Asset`] is served under.
+pub const PREFIX: &str = "/static/";
+
+/// Absolute URL for an embedded asset, valid from any route.
+pub fn url(name: &str) -> String {
+    format!("{PREFIX}{name}")
+}
+
+/// Absolute URL of the fingerprinted stylesheet produced by `build.rs`.
+pub fn stylesheet_url() -> String {
+    url(env!("BASE_CSS"))
+}
+
+/// Serves `/static/{*file}`. `file` arrives percent-decoded, so encoded traversal
+/// attempts like `%2e%2e%2f` are caught by [`is_safe_path`] as well.
+pub async fn handler(Path(file): Path<String>, headers: HeaderMap) -> Response {
+    if !is_safe_path(&file) {
+        return (StatusCode::BAD_REQUEST, "Bad asset path").into_response();
+    }
+
+    StaticFile(file).respond(headers.get(header::IF_NONE_MATCH))
+}
+
+/// Relative paths made of plain names only: no `..` or empty segments, no
+/// backslashes or other characters that could be reinterpreted on disk.
+fn is_safe_path(path: &str) -> bool {
+    !path.is_empty()
+        && path
+            .split('/')
+            .all(|seg| !seg.is_empty() && seg != "." && seg != "..")
+        && path
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_' | b'/'))
+}
+
+pub struct StaticFile<T>(pub T);
+
+impl<T> StaticFile<T>
+where
+    T: Into<String>,
+{
+    /// Serves the asset with a strong ETag, answering `304 Not Modified` when
+    /// `if_none_match` already names it.
+    pub fn respond(self, if_none_match: Option<&HeaderValue>) -> Response {
+        let path = self.0.into();
+
+        // A bare 404: this is answered to stylesheet and script loads, not navigation.
+        let Some(content) = Asset::get(path.as_str()) else {
+            return (StatusCode::NOT_FOUND, "Not found").into_response();
+        };
+
+        let etag = etag(&content.metadata.sha256_hash());
+        let cache_control = if is_fingerprinted(&path) {
+            "public, max-age=31536000, immutable"
+        } else {
+            "no-cache"
+        };
+        let headers = [
+            (header::ETAG, etag.clone()),
+            (header::CACHE_CONTROL, cache_control.to_owned()),
+        ];
+
+        if if_none_match.is_some_and(|v| etag_matches(v, &etag)) {
+            return (StatusCode::NOT_MODIFIED, headers).into_response();
+        }
+
+        let mime = mime_guess::from_path(path).first_or_octet_stream();
+        (
+            [(header::CONTENT_TYPE, mime.as_ref())],
+            headers,
+            content.data,
+        )
+            .into_response()
+    }
+}
+
+impl<T> IntoResponse for StaticFile<T>
+where
+    T: Into<String>,
+{
+    fn into_response(self) -> Response {
+        self.respond(None)
+    }
+}
+
+fn etag(hash: &[u8]) -> String {
+    let hex: String = hash[..16].iter().map(|b| format!("{b:02x}")).collect();
+    format!("\"{hex}\"")
+}
+
+/// Weak comparison as required for `If-None-Match` (RFC 9110 13.1.2).
+fn etag_matches(if_none_match: &HeaderValue, etag: &str) -> bool {
+    let Ok(value) = if_none_match.to_str() else {
+        return false;
+    };
+    value
+        .split(',')
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+/// Whether the file name carries a content hash (`name.<hex>.ext`), which makes it
+/// safe to cache forever.
+fn is_fingerprinted(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let mut parts = name.rsplit('.');
+    let (_ext, hash) = (parts.next(), parts.next());
+    parts.next().is_some()
+        && hash.is_some_and(|h| h.len() >= 8 && h.bytes().all(|b| b.is_ascii_hexdigit()))
+}
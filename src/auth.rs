@@ -0,0 +1,371 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{FromRef, Query, Request, State},
+    http::{header, request::Parts, HeaderName, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+    Extension, Form, Json,
+};
+use axum_extra::extract::{
+    cookie::{Cookie, Key, SameSite},
+    SignedCookieJar,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use maud::{html, Markup};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha512};
+use subtle::ConstantTimeEq;
+use tracing::{info, warn};
+use url::{form_urlencoded::byte_serialize, Url};
+
+use crate::{
+    config::Config,
+    html::{card, centered, error_card},
+    security, AppState,
+};
+
+const SESSION_COOKIE: &str = "my_search_session";
+const SESSION_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Routes reachable without signing in: probes, metrics scraping, assets and
+/// the login flow itself.
+const PUBLIC_PATHS: &[&str] = &["/healthz", "/readyz", "/metrics", "/login", "/logout"];
+const PUBLIC_PREFIXES: &[&str] = &["/static/"];
+
+/// The signed-in user, added to request extensions by [`require`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct User {
+    pub name: String,
+}
+
+impl User {
+    /// Whom per-user state like history and saved searches belongs to: the
+    /// user's name, or `None` when authentication is off and everyone is the
+    /// same user.
+    pub fn owner(user: &Option<Extension<User>>) -> Option<String> {
+        user.as_ref().map(|Extension(u)| u.name.clone())
+    }
+}
+
+/// One way of identifying the caller. Authenticators are tried in order and the
+/// first one to return a user wins.
+pub trait Authenticator: Send + Sync {
+    fn authenticate(&self, req: &Parts) -> Option<User>;
+}
+
+/// Static accounts from configuration, checked against `Authorization: Basic`.
+pub struct BasicUsers(HashMap<String, String>);
+
+impl BasicUsers {
+    pub fn verify(&self, name: &str, password: &str) -> bool {
+        let expected = self.0.get(name).map(String::as_bytes).unwrap_or_default();
+        // Compare even for unknown users so timing doesn't reveal which names exist.
+        let matches: bool = expected.ct_eq(password.as_bytes()).into();
+        matches && self.0.contains_key(name)
+    }
+}
+
+impl Authenticator for BasicUsers {
+    fn authenticate(&self, req: &Parts) -> Option<User> {
+        let value = req.headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+        let encoded = value.strip_prefix("Basic ")?;
+        let decoded = String::from_utf8(BASE64.decode(encoded).ok()?).ok()?;
+        let (name, password) = decoded.split_once(':')?;
+        self.verify(name, password)
+            .then(|| User { name: name.into() })
+    }
+}
+
+/// The signed session cookie set by the login page. Sessions of users that have
+/// since been removed from the configuration are rejected.
+pub struct Session {
+    key: Key,
+    users: HashSet<String>,
+}
+
+impl Authenticator for Session {
+    fn authenticate(&self, req: &Parts) -> Option<User> {
+        let jar = SignedCookieJar::from_headers(&req.headers, self.key.clone());
+        let value = jar.get(SESSION_COOKIE)?;
+        let (name, expires) = value.value().rsplit_once('|')?;
+        let expires = UNIX_EPOCH + Duration::from_secs(expires.parse().ok()?);
+        (expires > SystemTime::now() && self.users.contains(name))
+            .then(|| User { name: name.into() })
+    }
+}
+
+/// `Authorization: Bearer <key>` or `X-Api-Key`, accepted on `/api/` routes only.
+pub struct ApiKeys(HashMap<String, String>);
+
+impl Authenticator for ApiKeys {
+    fn authenticate(&self, req: &Parts) -> Option<User> {
+        if !req.uri.path().starts_with("/api/") {
+            return None;
+        }
+        let bearer = req
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        let key = bearer.or_else(|| req.headers.get("x-api-key")?.to_str().ok())?;
+        let name = self
+            .0
+            .iter()
+            .find(|(k, _)| bool::from(k.as_bytes().ct_eq(key.as_bytes())))
+            .map(|(_, name)| name)?;
+        Some(User { name: name.clone() })
+    }
+}
+
+/// A header such as `X-Forwarded-User` set by a reverse proxy that already
+/// authenticated the user. Only enable this when the proxy is the sole way in.
+pub struct ProxyHeader(HeaderName);
+
+impl Authenticator for ProxyHeader {
+    fn authenticate(&self, req: &Parts) -> Option<User> {
+        let name = req.headers.get(&self.0)?.to_str().ok()?.trim();
+        (!name.is_empty()).then(|| User { name: name.into() })
+    }
+}
+
+pub struct Auth {
+    authenticators: Vec<Box<dyn Authenticator>>,
+    basic: Option<BasicUsers>,
+    key: Key,
+}
+
+impl Auth {
+    pub fn from_config(config: &Config) -> Self {
+        let key = match &config.secret {
+            // Key wants 64 bytes of key material; stretch the secret to that.
+            Some(secret) if secret.len() >= 32 => Key::from(&Sha512::digest(secret.as_bytes())),
+            Some(_) => {
+                warn!("MY_SEARCH_SECRET is shorter than 32 bytes, using a random key");
+                Key::generate()
+            }
+            None => Key::generate(),
+        };
+
+        let mut authenticators: Vec<Box<dyn Authenticator>> = Vec::new();
+        if let Some(header) = &config.auth_proxy_header {
+            match HeaderName::try_from(header.as_str()) {
+                Ok(name) => authenticators.push(Box::new(ProxyHeader(name))),
+                Err(_) => warn!(header, "ignoring invalid auth proxy header name"),
+            }
+        }
+        if !config.api_keys.is_empty() {
+            authenticators.push(Box::new(ApiKeys(config.api_keys.clone())));
+        }
+        let basic = (!config.basic_users.is_empty()).then(|| {
+            authenticators.push(Box::new(BasicUsers(config.basic_users.clone())));
+            authenticators.push(Box::new(Session {
+                key: key.clone(),
+                users: config.basic_users.keys().cloned().collect(),
+            }));
+            BasicUsers(config.basic_users.clone())
+        });
+
+        Auth {
+            authenticators,
+            basic,
+            key,
+        }
+    }
+
+    /// The key cookies are signed with, derived from `MY_SEARCH_SECRET` or random.
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    /// Without any configured authenticator the instance is open, as before.
+    pub fn enabled(&self) -> bool {
+        !self.authenticators.is_empty()
+    }
+
+    pub fn authenticate(&self, req: &Parts) -> Option<User> {
+        self.authenticators.iter().find_map(|a| a.authenticate(req))
+    }
+}
+
+impl FromRef<AppState> for Key {
+    fn from_ref(state: &AppState) -> Self {
+        state.auth.key.clone()
+    }
+}
+
+fn is_public(path: &str) -> bool {
+    PUBLIC_PATHS.contains(&path) || PUBLIC_PREFIXES.iter().any(|p| path.starts_with(p))
+}
+
+/// Rejects unauthenticated requests when authentication is configured, and
+/// attaches the [`User`] to the request otherwise.
+pub async fn require(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    if !state.auth.enabled() {
+        return next.run(req).await;
+    }
+
+    let (mut parts, body) = req.into_parts();
+    if let Some(user) = state.auth.authenticate(&parts) {
+        parts.extensions.insert(user);
+        return next.run(Request::from_parts(parts, body)).await;
+    }
+    if is_public(parts.uri.path()) {
+        return next.run(Request::from_parts(parts, body)).await;
+    }
+
+    if parts.uri.path().starts_with("/api/") {
+        let body = Json(json!({
+            "error": "unauthorized",
+            "message": "an API key is required",
+        }));
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            body,
+        )
+            .into_response();
+    }
+
+    if state.auth.basic.is_some() {
+        let next_path = parts
+            .uri
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or("/");
+        let next_path: String = byte_serialize(next_path.as_bytes()).collect();
+        return Redirect::to(&format!("/login?next={next_path}")).into_response();
+    }
+
+    (
+        StatusCode::UNAUTHORIZED,
+        centered(
+            "Sign-in Required",
+            "max-w-md",
+            error_card(
+                "Sign-in Required",
+                "This instance only accepts signed-in users.",
+                "Open it through your organisation's sign-in proxy.",
+            ),
+        ),
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+pub struct LoginQuery {
+    next: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LoginForm {
+    user: String,
+    password: String,
+    next: Option<String>,
+}
+
+/// Only same-site paths, so the login form can't be used as an open redirect.
+/// Browsers drop tabs and newlines and treat `\` like `/`, so anything with
+/// those is refused outright; what is left must resolve against a fixed base
+/// without changing its origin.
+fn safe_next(next: Option<&str>) -> &str {
+    let same_site = |n: &str| {
+        if !n.starts_with('/')
+            || n.starts_with("//")
+            || n.contains('\\')
+            || n.chars().any(|c| c.is_ascii_control() || c.is_whitespace())
+        {
+            return false;
+        }
+        let base = Url::parse("http://localhost/").expect("valid base");
+        base.join(n).is_ok_and(|url| url.origin() == base.origin())
+    };
+    match next {
+        Some(n) if same_site(n) => n,
+        _ => "/",
+    }
+}
+
+pub async fn login_page(Query(query): Query<LoginQuery>) -> Markup {
+    login_form(safe_next(query.next.as_deref()), None)
+}
+
+pub async fn login(
+    State(state): State<AppState>,
+    jar: SignedCookieJar,
+    parts: Parts,
+    Form(form): Form<LoginForm>,
+) -> Response {
+    let next = safe_next(form.next.as_deref());
+    let valid = state
+        .auth
+        .basic
+        .as_ref()
+        .is_some_and(|b| b.verify(&form.user, &form.password));
+    if !valid {
+        warn!("failed login");
+        return (
+            StatusCode::UNAUTHORIZED,
+            login_form(next, Some("Unknown user or wrong password.")),
+        )
+            .into_response();
+    }
+
+    info!(user = form.user, "signed in");
+    let expires = SystemTime::now() + SESSION_TTL;
+    let expires_secs = expires.duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let cookie = Cookie::build((SESSION_COOKIE, format!("{}|{expires_secs}", form.user)))
+        .path("/")
+        .http_only(true)
+        .secure(security::over_tls(&parts))
+        .same_site(SameSite::Lax)
+        .max_age(SESSION_TTL.try_into().unwrap());
+    (jar.add(cookie), Redirect::to(next)).into_response()
+}
+
+pub async fn logout(jar: SignedCookieJar) -> impl IntoResponse {
+    (
+        jar.remove(Cookie::build(SESSION_COOKIE).path("/")),
+        Redirect::to("/login"),
+    )
+}
+
+fn login_form(next: &str, error: Option<&str>) -> Markup {
+    centered(
+        "Sign in",
+        "max-w-md",
+        card(html! {
+            h1 class="text-2xl font-bold text-center mb-6 logo-text" { "Sign in" }
+            @if let Some(error) = error {
+                p class="text-center text-red-400 mb-4" { (error) }
+            }
+            form class="space-y-4" method="post" action="/login" {
+                input type="hidden" name="next" value=(next);
+                input
+                    type="text"
+                    name="user"
+                    placeholder="User name"
+                    autocomplete="username"
+                    class="w-full px-4 py-3 bg-slate-800/50 text-white placeholder-slate-400 border border-slate-700/50 rounded-xl focus:outline-none focus:border-red-500/50"
+                    autofocus
+                    required;
+                input
+                    type="password"
+                    name="password"
+                    placeholder="Password"
+                    autocomplete="current-password"
+                    class="w-full px-4 py-3 bg-slate-800/50 text-white placeholder-slate-400 border border-slate-700/50 rounded-xl focus:outline-none focus:border-red-500/50"
+                    required;
+                button
+                    type="submit"
+                    class="lift-button block w-full py-3 bg-blue-800 hover:bg-blue-700 text-white text-lg font-semibold rounded-xl transition-all duration-300" {
+                    "Sign in"
+                }
+            }
+        }),
+    )
+}
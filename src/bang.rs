@@ -0,0 +1,30 @@
+/// `!name` shortcuts that send a query to one provider, e.g. `!gh box error`.
+pub const BANGS: &[(&str, &str)] = &[("gh", "github")];
+
+/// The provider a bang such as `gh` or `!gh` stands for.
+pub fn provider(bang: &str) -> Option<&'static str> {
+    let bang = bang.strip_prefix('!').unwrap_or(bang);
+    BANGS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(bang))
+        .map(|(_, provider)| *provider)
+}
+
+/// Splits the first known bang out of `query`, wherever it appears. Unknown
+/// bangs are left in place as ordinary search terms.
+pub fn split(query: &str) -> (Option<&'static str>, String) {
+    let mut found = None;
+    let terms: Vec<&str> = query
+        .split_whitespace()
+        .filter(|word| {
+            if found.is_none() && word.starts_with('!') {
+                if let Some(p) = provider(word) {
+                    found = Some(p);
+                    return false;
+                }
+            }
+            true
+        })
+        .collect();
+    (found, terms.join(" "))
+}
@@ -0,0 +1,37 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Expired entries are swept out once the cache grows past this.
+const SWEEP_AT: usize = 1000;
+
+/// In-memory map whose entries expire `ttl` after they were inserted.
+pub struct Cache<V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Instant, V)>>,
+}
+
+impl<V: Clone> Cache<V> {
+    pub fn new(ttl: Duration) -> Self {
+        Cache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<V> {
+        let entries = self.entries.lock().unwrap();
+        let (at, value) = entries.get(key)?;
+        (at.elapsed() < self.ttl).then(|| value.clone())
+    }
+
+    pub fn insert(&self, key: String, value: V) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= SWEEP_AT {
+            entries.retain(|_, (at, _)| at.elapsed() < self.ttl);
+        }
+        entries.insert(key, (Instant::now(), value));
+    }
+}
@@ -0,0 +1,256 @@
+use std::{collections::HashMap, env, path::PathBuf, time::Duration};
+
+/// Runtime configuration, read from `MY_SEARCH_*` environment variables.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub port: u16,
+    /// Listen on this Unix domain socket instead of TCP.
+    pub unix_socket: Option<PathBuf>,
+    /// File mode applied to `unix_socket` after it is created.
+    pub unix_socket_mode: u32,
+    /// Serve HTTPS on `port` instead of plain HTTP.
+    pub tls: Option<TlsConfig>,
+    pub log_format: LogFormat,
+    /// How long in-flight requests get to finish after SIGTERM/SIGINT.
+    pub drain_timeout: Duration,
+    /// Base URL of the GitHub REST API, without a trailing slash.
+    pub github_api_url: String,
+    /// Token for GitHub searches, unless the user set their own in `/settings`.
+    pub github_token: Option<String>,
+    /// Strip tracking parameters (`utm_*`, `fbclid`, ...) from result links.
+    pub clean_links: bool,
+    /// Accounts for the login page and HTTP basic auth, user name to password.
+    pub basic_users: HashMap<String, String>,
+    /// Keys accepted on `/api/` routes, key to the name requests are attributed to.
+    pub api_keys: HashMap<String, String>,
+    /// Header set by a trusted reverse proxy that names the signed-in user.
+    pub auth_proxy_header: Option<String>,
+    /// Secret for signing cookies, at least 32 bytes. Random per process when unset,
+    /// which signs everyone out on restart.
+    pub secret: Option<String>,
+    /// Where profiles and other state are kept. In memory only when unset.
+    pub data_dir: Option<PathBuf>,
+    /// Searches per client from the HTML page; `None` turns the limit off.
+    pub search_rate_limit: Option<Rate>,
+    /// Calls per client to the JSON API; `None` turns the limit off.
+    pub api_rate_limit: Option<Rate>,
+    /// Identify clients by `X-Forwarded-For`. Only set this behind a proxy that
+    /// overwrites or appends to the header. Without it, clients on a Unix
+    /// socket have no address and all anonymous ones share one rate limit.
+    pub trust_forwarded_for: bool,
+    /// Wait before the first webhook retry; doubled for each one after.
+    pub webhook_retry_delay: Duration,
+    /// Let webhooks post to loopback, private and link-local addresses, e.g. a
+    /// receiver on the same network. Off by default so that saved searches
+    /// can't be used to reach services that are only meant to be internal.
+    pub webhook_allow_private: bool,
+    /// Most results one export fetches, page by page.
+    pub export_limit: usize,
+    /// Settings that were set but couldn't be used, for logging once logging
+    /// is set up.
+    pub warnings: Vec<String>,
+}
+
+/// `burst` requests per `per`, e.g. `30/min`. Unused allowance accumulates up to
+/// `burst`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rate {
+    pub burst: u32,
+    pub per: Duration,
+}
+
+impl Rate {
+    pub fn per_sec(self) -> f64 {
+        self.burst as f64 / self.per.as_secs_f64()
+    }
+
+    /// Parses `30/min`, `2/s` or `500/h`.
+    fn parse(v: &str) -> Option<Rate> {
+        let (burst, per) = v.trim().split_once('/')?;
+        let per = match per {
+            "s" | "sec" => Duration::from_secs(1),
+            "m" | "min" => Duration::from_secs(60),
+            "h" | "hour" => Duration::from_secs(60 * 60),
+            _ => return None,
+        };
+        let burst = burst.parse().ok().filter(|b| *b > 0)?;
+        Some(Rate { burst, per })
+    }
+
+    /// A rate limit setting: a rate, or `off` to turn the limit off. `None`
+    /// for anything else, which shouldn't lift the limit by accident.
+    fn setting(v: &str) -> Option<Option<Rate>> {
+        if v.trim() == "off" {
+            return Some(None);
+        }
+        Rate::parse(v).map(Some)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// PEM certificate chain. Reloaded when the file changes.
+    pub cert: PathBuf,
+    /// PEM private key. Reloaded when the file changes.
+    pub key: PathBuf,
+    /// Plain HTTP port that redirects everything to HTTPS.
+    pub redirect_port: Option<u16>,
+    /// How often `cert` and `key` are checked for changes.
+    pub reload_interval: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            port: 2772,
+            unix_socket: None,
+            unix_socket_mode: 0o660,
+            tls: None,
+            log_format: LogFormat::Pretty,
+            drain_timeout: Duration::from_secs(10),
+            github_api_url: "https://api.github.com".into(),
+            github_token: None,
+            clean_links: false,
+            basic_users: HashMap::new(),
+            api_keys: HashMap::new(),
+            auth_proxy_header: None,
+            secret: None,
+            data_dir: None,
+            search_rate_limit: Some(Rate {
+                burst: 30,
+                per: Duration::from_secs(60),
+            }),
+            api_rate_limit: Some(Rate {
+                burst: 60,
+                per: Duration::from_secs(60),
+            }),
+            trust_forwarded_for: false,
+            webhook_retry_delay: Duration::from_secs(5),
+            webhook_allow_private: false,
+            // GitHub's search API doesn't go past the first 1000 results anyway.
+            export_limit: 1000,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        let mut config = Config::default();
+
+        if let Some(port) = env::var("MY_SEARCH_PORT").ok().and_then(|v| v.parse().ok()) {
+            config.port = port;
+        }
+        if let Some(path) = env::var_os("MY_SEARCH_UNIX_SOCKET") {
+            config.unix_socket = Some(path.into());
+        }
+        if let Some(mode) = env::var("MY_SEARCH_UNIX_SOCKET_MODE")
+            .ok()
+            .and_then(|v| u32::from_str_radix(&v, 8).ok())
+        {
+            config.unix_socket_mode = mode;
+        }
+        if let (Some(cert), Some(key)) = (
+            env::var_os("MY_SEARCH_TLS_CERT"),
+            env::var_os("MY_SEARCH_TLS_KEY"),
+        ) {
+            config.tls = Some(TlsConfig {
+                cert: cert.into(),
+                key: key.into(),
+                redirect_port: env::var("MY_SEARCH_HTTP_REDIRECT_PORT")
+                    .ok()
+                    .and_then(|v| v.parse().ok()),
+                reload_interval: env::var("MY_SEARCH_TLS_RELOAD_INTERVAL")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .map_or(Duration::from_secs(30), Duration::from_secs),
+            });
+        }
+        if let Ok("json") = env::var("MY_SEARCH_LOG_FORMAT").as_deref() {
+            config.log_format = LogFormat::Json;
+        }
+        if let Some(secs) = env::var("MY_SEARCH_DRAIN_TIMEOUT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            config.drain_timeout = Duration::from_secs(secs);
+        }
+        if let Ok(url) = env::var("MY_SEARCH_GITHUB_API_URL") {
+            config.github_api_url = url.trim_end_matches('/').into();
+        }
+        if let Ok(token) = env::var("MY_SEARCH_GITHUB_TOKEN") {
+            config.github_token = Some(token);
+        }
+        if let Ok(v) = env::var("MY_SEARCH_CLEAN_LINKS") {
+            config.clean_links = matches!(v.as_str(), "1" | "true" | "yes");
+        }
+        // name:password,name:password
+        if let Ok(v) = env::var("MY_SEARCH_BASIC_USERS") {
+            config.basic_users = pairs(&v).collect();
+        }
+        // name:key,name:key
+        if let Ok(v) = env::var("MY_SEARCH_API_KEYS") {
+            config.api_keys = pairs(&v).map(|(name, key)| (key, name)).collect();
+        }
+        if let Ok(header) = env::var("MY_SEARCH_AUTH_PROXY_HEADER") {
+            config.auth_proxy_header = Some(header);
+        }
+        if let Ok(secret) = env::var("MY_SEARCH_SECRET") {
+            config.secret = Some(secret);
+        }
+        if let Some(dir) = env::var_os("MY_SEARCH_DATA_DIR") {
+            config.data_dir = Some(dir.into());
+        }
+        // 30/min, or "off"
+        if let Ok(v) = env::var("MY_SEARCH_RATE_LIMIT") {
+            match Rate::setting(&v) {
+                Some(rate) => config.search_rate_limit = rate,
+                None => config.warnings.push(format!(
+                    "MY_SEARCH_RATE_LIMIT={v:?} is not a rate like 30/min or \"off\"; keeping the default"
+                )),
+            }
+        }
+        if let Ok(v) = env::var("MY_SEARCH_API_RATE_LIMIT") {
+            match Rate::setting(&v) {
+                Some(rate) => config.api_rate_limit = rate,
+                None => config.warnings.push(format!(
+                    "MY_SEARCH_API_RATE_LIMIT={v:?} is not a rate like 30/min or \"off\"; keeping the default"
+                )),
+            }
+        }
+        if let Ok(v) = env::var("MY_SEARCH_TRUST_FORWARDED_FOR") {
+            config.trust_forwarded_for = matches!(v.as_str(), "1" | "true" | "yes");
+        }
+        if let Some(secs) = env::var("MY_SEARCH_WEBHOOK_RETRY_DELAY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            config.webhook_retry_delay = Duration::from_secs(secs);
+        }
+        if let Ok(v) = env::var("MY_SEARCH_WEBHOOK_ALLOW_PRIVATE") {
+            config.webhook_allow_private = matches!(v.as_str(), "1" | "true" | "yes");
+        }
+        if let Some(limit) = env::var("MY_SEARCH_EXPORT_LIMIT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            config.export_limit = limit;
+        }
+
+        config
+    }
+}
+
+/// Parses `a:1,b:2` into `("a", "1"), ("b", "2")`, skipping malformed entries.
+fn pairs(v: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    v.split(',').filter_map(|pair| {
+        let (k, v) = pair.trim().split_once(':')?;
+        Some((k.to_owned(), v.to_owned()))
+    })
+}
@@ -0,0 +1,205 @@
+use std::fmt;
+
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use maud::Markup;
+use serde_json::json;
+
+use crate::html::{centered, error_card};
+
+#[derive(Debug)]
+pub enum Error {
+    /// The upstream could not be reached at all (DNS, connection refused, TLS, ...).
+    Network(reqwest::Error),
+    /// The upstream did not answer in time.
+    Timeout,
+    /// The upstream answered with a non-success status we have no better mapping for.
+    UpstreamStatus(StatusCode),
+    /// The upstream refused the request because our quota is used up.
+    /// `retry_after` is in seconds when the upstream told us.
+    RateLimited { retry_after: Option<u64> },
+    /// The upstream rejected our credentials.
+    Auth,
+    /// The upstream answered, but not with something we could parse.
+    Decode(String),
+    /// The query itself is invalid; retrying it unchanged will not help.
+    BadQuery(String),
+    /// The server is shutting down and gave up on the upstream request.
+    Cancelled,
+    /// This client searched more often than the instance allows.
+    TooManyRequests { retry_after: u64 },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::Network(_) => StatusCode::BAD_GATEWAY,
+            Error::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            Error::UpstreamStatus(_) => StatusCode::BAD_GATEWAY,
+            Error::RateLimited { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            Error::Auth => StatusCode::BAD_GATEWAY,
+            Error::Decode(_) => StatusCode::BAD_GATEWAY,
+            Error::BadQuery(_) => StatusCode::BAD_REQUEST,
+            Error::Cancelled => StatusCode::SERVICE_UNAVAILABLE,
+            Error::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    /// Short machine-readable label, used in logs and metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Network(_) => "network",
+            Error::Timeout => "timeout",
+            Error::UpstreamStatus(_) => "upstream_status",
+            Error::RateLimited { .. } => "rate_limited",
+            Error::Auth => "auth",
+            Error::Decode(_) => "decode",
+            Error::BadQuery(_) => "bad_query",
+            Error::Cancelled => "cancelled",
+            Error::TooManyRequests { .. } => "too_many_requests",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Error::Network(_) => "Search Provider Unreachable",
+            Error::Timeout => "Search Timed Out",
+            Error::UpstreamStatus(_) => "Search Provider Error",
+            Error::RateLimited { .. } => "Rate Limit Reached",
+            Error::Auth => "Search Provider Rejected Credentials",
+            Error::Decode(_) => "Unexpected Response",
+            Error::BadQuery(_) => "Invalid Search",
+            Error::Cancelled => "Server Restarting",
+            Error::TooManyRequests { .. } => "Slow Down",
+        }
+    }
+
+    /// What the user can do about it.
+    pub fn hint(&self) -> String {
+        match self {
+            Error::Network(_) => "Check your network connection and try again.".into(),
+            Error::Timeout => "The provider is slow right now. Try again in a moment.".into(),
+            Error::UpstreamStatus(_) => "The provider is having problems. Try again later.".into(),
+            Error::RateLimited {
+                retry_after: Some(secs),
+            } => format!("Too many searches. Try again in {}.", human_duration(*secs)),
+            Error::RateLimited { retry_after: None } => {
+                "Too many searches. Wait a little and try again.".into()
+            }
+            Error::Auth => "The configured provider token is missing or invalid.".into(),
+            Error::Decode(_) => "The provider sent something we did not understand.".into(),
+            Error::BadQuery(_) => "Change your search terms and try again.".into(),
+            Error::Cancelled => "Try again in a few seconds.".into(),
+            Error::TooManyRequests { retry_after } => {
+                format!("Try again in {}.", human_duration(*retry_after))
+            }
+        }
+    }
+
+    /// Seconds the client should wait before retrying, sent as `Retry-After`.
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            Error::RateLimited { retry_after } => *retry_after,
+            Error::TooManyRequests { retry_after } => Some(*retry_after),
+            _ => None,
+        }
+    }
+}
+
+fn human_duration(secs: u64) -> String {
+    match secs {
+        0..=1 => "a second".into(),
+        2..=59 => format!("{secs} seconds"),
+        60..=119 => "a minute".into(),
+        _ => format!("{} minutes", secs.div_ceil(60)),
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Network(e) => write!(f, "could not reach the search provider: {e}"),
+            Error::Timeout => write!(f, "the search provider did not respond in time"),
+            Error::UpstreamStatus(s) => write!(f, "the search provider responded with {s}"),
+            Error::RateLimited { .. } => write!(f, "the search provider rate limit was reached"),
+            Error::Auth => write!(f, "the search provider rejected our credentials"),
+            Error::Decode(msg) => write!(f, "could not read the provider response: {msg}"),
+            Error::BadQuery(msg) => write!(f, "{msg}"),
+            Error::Cancelled => write!(f, "the server is shutting down"),
+            Error::TooManyRequests { .. } => {
+                write!(f, "you are searching faster than this instance allows")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Network(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Error::Timeout
+        } else if e.is_decode() {
+            Error::Decode(e.to_string())
+        } else {
+            Error::Network(e.without_url())
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let mut resp = (self.status(), error_page(&self)).into_response();
+        if let Some(secs) = self.retry_after() {
+            resp.headers_mut()
+                .insert(header::RETRY_AFTER, secs.to_string().parse().unwrap());
+        }
+        resp
+    }
+}
+
+/// Renders an [`Error`] as JSON for the API instead of an HTML page.
+pub struct JsonError(pub Error);
+
+impl From<Error> for JsonError {
+    fn from(e: Error) -> Self {
+        JsonError(e)
+    }
+}
+
+impl IntoResponse for JsonError {
+    fn into_response(self) -> Response {
+        let e = self.0;
+        let body = Json(json!({
+            "error": e.kind(),
+            "message": e.to_string(),
+            "hint": e.hint(),
+        }));
+        let mut resp = (e.status(), body).into_response();
+        if let Some(secs) = e.retry_after() {
+            resp.headers_mut()
+                .insert(header::RETRY_AFTER, secs.to_string().parse().unwrap());
+        }
+        resp
+    }
+}
+
+fn error_page(e: &Error) -> Markup {
+    centered(
+        e.title(),
+        "max-w-md",
+        error_card(e.title(), &e.to_string(), &e.hint()),
+    )
+}
@@ -0,0 +1,314 @@
+use std::{borrow::Cow, fmt::Write, future, io};
+
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{header, HeaderValue},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    error::{Error, Result},
+    github, links,
+    profile::Profile,
+    ratelimit::Metered,
+    search_page, AppState,
+};
+
+/// Results per provider call, the most GitHub hands out at once.
+const PAGE_SIZE: u8 = 100;
+
+const CSV_HEADER: &str = "title,url,source,repository,state,labels,created_at,updated_at\r\n";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Jsonl,
+    Markdown,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Csv, Format::Jsonl, Format::Markdown];
+
+    pub fn parse(s: &str) -> Result<Format> {
+        Format::ALL
+            .into_iter()
+            .find(|f| f.as_str() == s)
+            .ok_or_else(|| Error::BadQuery(format!("unknown export format {s:?}")))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Jsonl => "jsonl",
+            Format::Markdown => "markdown",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Format::Csv => "CSV",
+            Format::Jsonl => "JSON Lines",
+            Format::Markdown => "Markdown",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Jsonl => "application/jsonl; charset=utf-8",
+            Format::Markdown => "text/markdown; charset=utf-8",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Jsonl => "jsonl",
+            Format::Markdown => "md",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    q: Option<String>,
+    format: Option<String>,
+}
+
+/// `/export?q=...&format=csv|jsonl|markdown`, offered as a download.
+pub async fn download(
+    State(state): State<AppState>,
+    profile: Profile,
+    metered: Option<Extension<Metered>>,
+    Query(export): Query<ExportQuery>,
+) -> Result<Response> {
+    let format = Format::parse(export.format.as_deref().unwrap_or("csv"))?;
+    let query = export.q.unwrap_or_default();
+    let metered = metered.map(|Extension(m)| m);
+    let mut resp = stream_results(state, profile, metered, query, format).await?;
+    let disposition = format!(
+        "attachment; filename=\"search-results.{}\"",
+        format.extension()
+    );
+    resp.headers_mut().insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&disposition).unwrap(),
+    );
+    Ok(resp)
+}
+
+/// Streams every result for `query`, up to `export_limit`, fetching the next
+/// page only once the previous one is written out. Each page after the first
+/// takes another token from the `metered` client's rate limit. A failing first
+/// page is an ordinary error response; a later one, or running out of tokens,
+/// cuts the body short, since the status has already gone out by then.
+pub async fn stream_results(
+    state: AppState,
+    profile: Profile,
+    metered: Option<Metered>,
+    query: String,
+    format: Format,
+) -> Result<Response> {
+    let first = search_page(&state, &profile, &query, 1, PAGE_SIZE).await?;
+    let source = profile.route(&query).0.unwrap_or_default();
+    // GitHub serves no results past the first 1000, however many match.
+    let limit = state
+        .config
+        .export_limit
+        .min(first.total_count.clamp(0, github::MAX_RESULTS) as usize);
+
+    let mut pages = Pages {
+        state,
+        profile,
+        metered,
+        query,
+        format,
+        source,
+        page: 1,
+        sent: 0,
+        limit,
+    };
+    let mut head = String::new();
+    if format == Format::Csv {
+        head.push_str(CSV_HEADER);
+    }
+    pages.write(&mut head, &first.items);
+
+    let rest = stream::unfold(pages, |mut pages| async move {
+        let chunk = pages.next().await?;
+        Some((chunk, pages))
+    });
+    let body = Body::from_stream(stream::once(future::ready(Ok(head))).chain(rest));
+    Ok(([(header::CONTENT_TYPE, format.content_type())], body).into_response())
+}
+
+struct Pages {
+    state: AppState,
+    profile: Profile,
+    /// `None` when the route isn't rate limited.
+    metered: Option<Metered>,
+    query: String,
+    format: Format,
+    source: &'static str,
+    page: u32,
+    sent: usize,
+    limit: usize,
+}
+
+impl Pages {
+    async fn next(&mut self) -> Option<io::Result<String>> {
+        if self.sent >= self.limit {
+            return None;
+        }
+        self.page += 1;
+        let res = async {
+            if let Some(metered) = &self.metered {
+                self.state.limiter.charge(metered)?;
+            }
+            search_page(
+                &self.state,
+                &self.profile,
+                &self.query,
+                self.page,
+                PAGE_SIZE,
+            )
+            .await
+        }
+        .await;
+        let res = match res {
+            Ok(res) => res,
+            Err(e) => {
+                warn!(page = self.page, error = %e, "export cut short");
+                self.limit = 0;
+                return Some(Err(io::Error::other(e.to_string())));
+            }
+        };
+        if res.items.is_empty() {
+            return None;
+        }
+        let mut chunk = String::new();
+        self.write(&mut chunk, &res.items);
+        Some(Ok(chunk))
+    }
+
+    fn write(&mut self, out: &mut String, items: &[github::Item]) {
+        let take = items.len().min(self.limit.saturating_sub(self.sent));
+        for item in &items[..take] {
+            let row = Row {
+                title: &item.title,
+                url: links::outbound(&item.html_url, self.state.config.clean_links),
+                source: self.source,
+                repository: item.repository(),
+                state: &item.state,
+                labels: item.labels.iter().map(|l| l.name.as_str()).collect(),
+                created_at: &item.created_at,
+                updated_at: &item.updated_at,
+            };
+            match self.format {
+                Format::Csv => row.csv(out),
+                Format::Jsonl => {
+                    out.push_str(&serde_json::to_string(&row).unwrap());
+                    out.push('\n');
+                }
+                Format::Markdown => row.markdown(out),
+            }
+        }
+        self.sent += take;
+    }
+}
+
+#[derive(Serialize)]
+struct Row<'a> {
+    title: &'a str,
+    /// `None` when the link failed validation.
+    url: Option<String>,
+    source: &'a str,
+    repository: &'a str,
+    state: &'a str,
+    labels: Vec<&'a str>,
+    created_at: &'a str,
+    updated_at: &'a str,
+}
+
+impl Row<'_> {
+    fn csv(&self, out: &mut String) {
+        let labels = self.labels.join(", ");
+        let fields = [
+            self.title,
+            self.url.as_deref().unwrap_or_default(),
+            self.source,
+            self.repository,
+            self.state,
+            &labels,
+            self.created_at,
+            self.updated_at,
+        ];
+        for (i, field) in fields.into_iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            csv_field(out, field);
+        }
+        out.push_str("\r\n");
+    }
+
+    fn markdown(&self, out: &mut String) {
+        let title = markdown_escape(self.title);
+        match &self.url {
+            // Parentheses would end the link target early.
+            Some(url) => {
+                let url = url.replace('(', "%28").replace(')', "%29");
+                let _ = write!(out, "- [{title}]({url})");
+            }
+            None => {
+                let _ = write!(out, "- {title}");
+            }
+        }
+        let _ = write!(
+            out,
+            " · {} · {}",
+            markdown_escape(self.repository),
+            self.state
+        );
+        if !self.labels.is_empty() {
+            let _ = write!(out, " · {}", markdown_escape(&self.labels.join(", ")));
+        }
+        let day = self.updated_at.get(..10).unwrap_or(self.updated_at);
+        let _ = writeln!(out, " · updated {day}");
+    }
+}
+
+/// Quotes a field when needed (RFC 4180). Fields that a spreadsheet would run
+/// as a formula get a leading `'`, since titles come from anyone on GitHub.
+fn csv_field(out: &mut String, field: &str) {
+    let field: Cow<str> = if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{field}").into()
+    } else {
+        field.into()
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        let _ = write!(out, "\"{}\"", field.replace('"', "\"\""));
+    } else {
+        out.push_str(&field);
+    }
+}
+
+fn markdown_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' | '\r' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
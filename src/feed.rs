@@ -0,0 +1,267 @@
+use std::{fmt::Write, time::Duration};
+
+use axum::{
+    extract::{Query, State},
+    http::{header, request::Parts},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::Deserialize;
+use time::{
+    format_description::well_known::{Rfc2822, Rfc3339},
+    OffsetDateTime,
+};
+use tracing::{field, instrument, Span};
+use url::form_urlencoded::byte_serialize;
+
+use crate::{
+    auth::User,
+    error::{Error, Result},
+    fetch_page, github, links,
+    metrics::METRICS,
+    profile::Profile,
+    security, AppState,
+};
+
+/// How long feed results are reused. Feed readers poll every few minutes; each
+/// poll shouldn't cost a provider call.
+pub const FEED_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Characters of the issue body used as the entry summary.
+const SUMMARY_LEN: usize = 300;
+
+#[derive(Deserialize)]
+pub struct FeedQuery {
+    q: Option<String>,
+    format: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Atom,
+    Rss,
+}
+
+/// `/feed?q=...&format=atom|rss`, Atom unless asked otherwise.
+pub async fn feed(
+    State(state): State<AppState>,
+    profile: Profile,
+    user: Option<Extension<User>>,
+    parts: Parts,
+    Query(feed): Query<FeedQuery>,
+) -> Result<Response> {
+    let format = match feed.format.as_deref() {
+        None | Some("atom") => Format::Atom,
+        Some("rss") => Format::Rss,
+        Some(other) => return Err(Error::BadQuery(format!("unknown feed format {other:?}"))),
+    };
+    let query = feed.q.unwrap_or_default();
+
+    // Results depend on whose token and which provider is used, so those are
+    // part of the key.
+    let user = User::owner(&user);
+    let key = format!(
+        "{}\n{:?}\n{}\n{query}",
+        user.unwrap_or_default(),
+        profile.route(&query).0,
+        profile.per_page,
+    );
+    let res = cached_search(&state, &profile, &query, key).await?;
+
+    let base = base_url(&parts);
+    let q = byte_serialize(query.as_bytes()).collect::<String>();
+    let feed = Feed {
+        title: format!("My Search: {query}"),
+        html_url: format!("{base}/?q={q}"),
+        self_url: match format {
+            Format::Atom => format!("{base}/feed?q={q}"),
+            Format::Rss => format!("{base}/feed?q={q}&format=rss"),
+        },
+        clean_links: state.config.clean_links,
+    };
+    let (content_type, body) = match format {
+        Format::Atom => ("application/atom+xml; charset=utf-8", feed.atom(&res)),
+        Format::Rss => ("application/rss+xml; charset=utf-8", feed.rss(&res)),
+    };
+    Ok(([(header::CONTENT_TYPE, content_type)], body).into_response())
+}
+
+/// The first page of results, reused from `feed_cache` for [`FEED_TTL`].
+#[instrument(name = "search", skip_all, fields(query_hash = field::Empty, page = 1, cache_hit = field::Empty, result_count = field::Empty))]
+async fn cached_search(
+    state: &AppState,
+    profile: &Profile,
+    query: &str,
+    key: String,
+) -> Result<github::Root> {
+    let cached = state.feed_cache.get(&key);
+    let outcome = if cached.is_some() { "hit" } else { "miss" };
+    Span::current().record("cache_hit", cached.is_some());
+    METRICS
+        .cache_lookups
+        .with_label_values(&["feed", outcome])
+        .inc();
+
+    if let Some(res) = cached {
+        Span::current().record("query_hash", state.query_hasher.hash(query));
+        Span::current().record("result_count", res.items.len());
+        return Ok(res);
+    }
+    let res = fetch_page(state, profile, query, 1, profile.per_page).await?;
+    state.feed_cache.insert(key, res.clone());
+    Ok(res)
+}
+
+/// Feeds need absolute links; build them from the address the reader used.
+fn base_url(req: &Parts) -> String {
+    let scheme = if security::over_tls(req) {
+        "https"
+    } else {
+        "http"
+    };
+    let host = req
+        .headers
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("localhost");
+    format!("{scheme}://{host}")
+}
+
+struct Feed {
+    title: String,
+    html_url: String,
+    self_url: String,
+    clean_links: bool,
+}
+
+impl Feed {
+    fn atom(&self, res: &github::Root) -> String {
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+        xml.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+        write_tag(&mut xml, "title", &self.title);
+        // Required unless every entry names its own author.
+        xml.push_str("<author><name>My Search</name></author>");
+        write_tag(&mut xml, "id", &self.self_url);
+        let _ = write!(
+            xml,
+            r#"<link rel="self" href="{}"/><link rel="alternate" type="text/html" href="{}"/>"#,
+            escape(&self.self_url),
+            escape(&self.html_url),
+        );
+        write_tag(&mut xml, "updated", &updated(res));
+
+        for item in &res.items {
+            xml.push_str("<entry>");
+            write_tag(&mut xml, "title", &item.title);
+            // The API URL never changes, even when the issue moves or is renamed.
+            write_tag(&mut xml, "id", &item.url);
+            let summary = summary(item);
+            match links::outbound(&item.html_url, self.clean_links) {
+                Some(url) => {
+                    let _ = write!(xml, r#"<link href="{}"/>"#, escape(&url));
+                }
+                // An entry without an alternate link must have content instead.
+                None => write_tag(
+                    &mut xml,
+                    "content",
+                    summary.as_deref().unwrap_or(&item.title),
+                ),
+            }
+            write_tag(&mut xml, "published", &item.created_at);
+            write_tag(&mut xml, "updated", &item.updated_at);
+            if let Some(body) = summary {
+                write_tag(&mut xml, "summary", &body);
+            }
+            xml.push_str("</entry>");
+        }
+        xml.push_str("</feed>");
+        xml
+    }
+
+    fn rss(&self, res: &github::Root) -> String {
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+        xml.push_str(r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"><channel>"#);
+        write_tag(&mut xml, "title", &self.title);
+        write_tag(&mut xml, "link", &self.html_url);
+        write_tag(&mut xml, "description", &self.title);
+        let _ = write!(
+            xml,
+            r#"<atom:link rel="self" type="application/rss+xml" href="{}"/>"#,
+            escape(&self.self_url),
+        );
+        if let Some(date) = rfc2822(&updated(res)) {
+            write_tag(&mut xml, "lastBuildDate", &date);
+        }
+
+        for item in &res.items {
+            xml.push_str("<item>");
+            write_tag(&mut xml, "title", &item.title);
+            if let Some(url) = links::outbound(&item.html_url, self.clean_links) {
+                write_tag(&mut xml, "link", &url);
+            }
+            let _ = write!(
+                xml,
+                r#"<guid isPermaLink="false">{}</guid>"#,
+                escape(&item.url)
+            );
+            if let Some(date) = rfc2822(&item.updated_at) {
+                write_tag(&mut xml, "pubDate", &date);
+            }
+            if let Some(body) = summary(item) {
+                write_tag(&mut xml, "description", &body);
+            }
+            xml.push_str("</item>");
+        }
+        xml.push_str("</channel></rss>");
+        xml
+    }
+}
+
+/// The most recent item update, or now for an empty feed.
+fn updated(res: &github::Root) -> String {
+    // GitHub timestamps are all UTC in the same format, so they sort as strings.
+    match res.items.iter().map(|i| &i.updated_at).max() {
+        Some(latest) => latest.clone(),
+        None => OffsetDateTime::now_utc().format(&Rfc3339).unwrap(),
+    }
+}
+
+fn rfc2822(rfc3339: &str) -> Option<String> {
+    let t = OffsetDateTime::parse(rfc3339, &Rfc3339).ok()?;
+    t.format(&Rfc2822).ok()
+}
+
+fn summary(item: &github::Item) -> Option<String> {
+    let body = item.body.as_deref()?.trim();
+    if body.is_empty() {
+        return None;
+    }
+    let mut summary: String = body.chars().take(SUMMARY_LEN).collect();
+    if summary.len() < body.len() {
+        summary.push('…');
+    }
+    Some(summary)
+}
+
+fn write_tag(xml: &mut String, tag: &str, text: &str) {
+    let _ = write!(xml, "<{tag}>{}</{tag}>", escape(text));
+}
+
+/// Escapes text and attribute values. Characters XML 1.0 doesn't allow at all
+/// are dropped, since an issue body can contain anything.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c < ' ' || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -1,5 +1,64 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use reqwest::{header::HeaderMap, StatusCode};
 use serde::{Deserialize, Serialize};
 
+use crate::error::Error;
+
+pub fn rate_limit_remaining(headers: &HeaderMap) -> Option<i64> {
+    headers
+        .get("x-ratelimit-remaining")?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+pub fn rate_limit_reset(headers: &HeaderMap) -> Option<SystemTime> {
+    let secs: u64 = headers
+        .get("x-ratelimit-reset")?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Maps a non-success GitHub response to an [`Error`].
+///
+/// GitHub signals both primary and secondary rate limits with 403 (sometimes 429),
+/// so the rate-limit headers are checked before treating 403 as an auth failure.
+pub fn status_error(status: StatusCode, headers: &HeaderMap) -> Error {
+    let header_u64 = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+    };
+
+    let retry_after = header_u64("retry-after").or_else(|| {
+        let reset = header_u64("x-ratelimit-reset")?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        Some(reset.saturating_sub(now))
+    });
+    let exhausted = header_u64("x-ratelimit-remaining") == Some(0);
+
+    match status {
+        StatusCode::TOO_MANY_REQUESTS => Error::RateLimited { retry_after },
+        StatusCode::FORBIDDEN if exhausted || headers.contains_key("retry-after") => {
+            Error::RateLimited { retry_after }
+        }
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Error::Auth,
+        StatusCode::UNPROCESSABLE_ENTITY => {
+            Error::BadQuery("GitHub could not process this search query".into())
+        }
+        _ => Error::UpstreamStatus(status),
+    }
+}
+
+/// The search API serves no results past this many, whatever `total_count` says.
+pub const MAX_RESULTS: i64 = 1000;
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Root {
@@ -14,8 +73,8 @@ pub struct Root {
 #[serde(rename_all = "camelCase")]
 pub struct Item {
     pub url: String,
-    // #[serde(rename = "repository_url")]
-    // pub repository_url: String,
+    #[serde(rename = "repository_url")]
+    pub repository_url: String,
     // #[serde(rename = "labels_url")]
     // pub labels_url: String,
     // #[serde(rename = "comments_url")]
@@ -30,17 +89,19 @@ pub struct Item {
     // pub number: i64,
     pub title: String,
     // pub user: User,
-    // pub labels: Vec<Label>,
-    // pub state: String,
+    pub labels: Vec<Label>,
+    pub state: String,
     // pub locked: bool,
     // pub assignee: Option<Assignee>,
     // pub assignees: Vec<Assignee2>,
     // // pub milestone: Value,
     // pub comments: i64,
-    // #[serde(rename = "created_at")]
-    // pub created_at: String,
-    // #[serde(rename = "updated_at")]
-    // pub updated_at: String,
+    /// RFC 3339, always in UTC.
+    #[serde(rename = "created_at")]
+    pub created_at: String,
+    /// RFC 3339, always in UTC.
+    #[serde(rename = "updated_at")]
+    pub updated_at: String,
     // // #[serde(rename = "closed_at")]
     // // pub closed_at: Value,
     // #[serde(rename = "author_association")]
@@ -52,7 +113,7 @@ pub struct Item {
     // pub draft: bool,
     // #[serde(rename = "pull_request")]
     // pub pull_request: PullRequest,
-    // pub body: Option<String>,
+    pub body: Option<String>,
     // pub reactions: Reactions,
     // #[serde(rename = "timeline_url")]
     // pub timeline_url: String,
@@ -63,6 +124,28 @@ pub struct Item {
     // pub score: f64,
 }
 
+impl Item {
+    /// `owner/name`, taken from the API URL of the repository.
+    pub fn repository(&self) -> &str {
+        self.repository_url
+            .split_once("/repos/")
+            .map_or("", |(_, repo)| repo)
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Label {
+    // pub id: i64,
+    // #[serde(rename = "node_id")]
+    // pub node_id: String,
+    // pub url: String,
+    pub name: String,
+    // pub color: String,
+    // pub default: bool,
+    // pub description: Option<String>,
+}
+
 // #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 // #[serde(rename_all = "camelCase")]
 // pub struct User {
@@ -105,19 +188,6 @@ pub struct Item {
 //
 // #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 // #[serde(rename_all = "camelCase")]
-// pub struct Label {
-//     pub id: i64,
-//     #[serde(rename = "node_id")]
-//     pub node_id: String,
-//     pub url: String,
-//     pub name: String,
-//     pub color: String,
-//     pub default: bool,
-//     pub description: Option<String>,
-// }
-//
-// #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-// #[serde(rename_all = "camelCase")]
 // pub struct Assignee {
 //     pub login: String,
 //     pub id: i64,
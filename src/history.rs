@@ -0,0 +1,183 @@
+use std::time::SystemTime;
+
+use axum::{
+    extract::{Path, Query, State},
+    response::Response,
+    Extension,
+};
+use maud::{html, Markup};
+use serde::{Deserialize, Serialize};
+use url::form_urlencoded::byte_serialize;
+
+use crate::{
+    auth::User,
+    html::{ago, card, column},
+    profile::Profile,
+    store::{unix_now, unix_time},
+    AppState,
+};
+
+/// Oldest entries beyond this many per user are dropped.
+const MAX_PER_USER: usize = 1000;
+
+/// Searches of all users. Anonymous searches are recorded without a user; they
+/// only happen when authentication is off, where everyone is the same user.
+#[derive(Default, Serialize, Deserialize)]
+pub struct History {
+    next_id: u64,
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: u64,
+    pub user: Option<String>,
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+    /// The query as typed, bang included, so re-running it searches the same way.
+    pub query: String,
+    pub providers: Vec<String>,
+    pub result_count: i64,
+}
+
+impl Entry {
+    fn at(&self) -> SystemTime {
+        unix_time(self.at)
+    }
+}
+
+/// Records a finished search unless the user turned history off.
+pub fn record(
+    state: &AppState,
+    user: Option<&User>,
+    profile: &Profile,
+    query: &str,
+    result_count: i64,
+) {
+    if !profile.record_history {
+        return;
+    }
+    let user = user.map(|u| u.name.clone());
+    let (provider, _) = profile.route(query);
+    let at = unix_now();
+
+    // Written out in the background: this runs on every search.
+    state.history.update_later(|h| {
+        let entry = Entry {
+            id: h.next_id,
+            user: user.clone(),
+            at,
+            query: query.into(),
+            providers: provider.into_iter().map(Into::into).collect(),
+            result_count,
+        };
+        h.next_id += 1;
+        h.entries.push(entry);
+        if h.entries.iter().filter(|e| e.user == user).count() > MAX_PER_USER {
+            let oldest = h.entries.iter().position(|e| e.user == user).unwrap();
+            h.entries.remove(oldest);
+        }
+    });
+}
+
+#[derive(Deserialize)]
+pub struct Filter {
+    filter: Option<String>,
+}
+
+pub async fn history_page(
+    State(state): State<AppState>,
+    profile: Profile,
+    user: Option<Extension<User>>,
+    Query(filter): Query<Filter>,
+) -> Markup {
+    let owner = User::owner(&user);
+    let filter = filter.filter.unwrap_or_default();
+    let needle = filter.trim().to_lowercase();
+    let entries: Vec<Entry> = state.history.read(|h| {
+        h.entries
+            .iter()
+            .rev()
+            .filter(|e| e.user == owner && e.query.to_lowercase().contains(&needle))
+            .cloned()
+            .collect()
+    });
+
+    column(
+        "History",
+        html! {
+            h1 class="text-3xl font-bold text-white" { "History" }
+            @if !profile.record_history {
+                (card(html! {
+                    p class="text-slate-300" {
+                        "Recording is turned off in " a href="/settings" class="text-blue-400" { "settings" } "."
+                    }
+                }))
+            }
+            (card(html! {
+                form class="flex gap-4 mb-4" method="get" action="/history" {
+                    input
+                        type="text"
+                        name="filter"
+                        value=(filter)
+                        placeholder="Filter searches..."
+                        class="flex-1 px-4 py-2 bg-slate-800/50 text-white placeholder-slate-400 border border-slate-700/50 rounded-xl focus:outline-none";
+                    button type="submit" class="lift-button px-4 py-2 bg-blue-800 hover:bg-blue-700 text-white rounded-xl" { "Filter" }
+                }
+                @if entries.is_empty() {
+                    p class="text-center text-slate-400 py-8" { "No searches recorded." }
+                } @else {
+                    div class="space-y-4" {
+                        @for e in &entries {
+                            (entry(e))
+                        }
+                    }
+                    form class="mt-4" method="post" action="/history/clear" {
+                        button type="submit" class="text-sm text-slate-400 hover:text-slate-300 underline" {
+                            "Clear history"
+                        }
+                    }
+                }
+            }))
+        },
+    )
+}
+
+fn entry(e: &Entry) -> Markup {
+    let rerun = format!(
+        "/?q={}",
+        byte_serialize(e.query.as_bytes()).collect::<String>()
+    );
+    html! {
+        div class="result-item rounded-xl p-4 flex items-center gap-4" {
+            div class="flex-1" {
+                a href=(rerun) class="text-lg font-semibold text-white" { (e.query) }
+                p class="text-sm text-slate-400" {
+                    (e.providers.join(", ")) " · " (e.result_count) " results · " (ago(e.at()))
+                }
+            }
+            a href=(rerun) class="text-sm text-blue-400 hover:text-blue-300" { "Run again" }
+            form method="post" action={ "/history/" (e.id) "/delete" } {
+                button type="submit" class="text-sm text-slate-400 hover:text-red-400" { "Delete" }
+            }
+        }
+    }
+}
+
+pub async fn delete_entry(
+    State(state): State<AppState>,
+    user: Option<Extension<User>>,
+    Path(id): Path<u64>,
+) -> Response {
+    let owner = User::owner(&user);
+    state.history.update_and_redirect("/history", |h| {
+        h.entries.retain(|e| !(e.id == id && e.user == owner))
+    })
+}
+
+pub async fn clear(State(state): State<AppState>, user: Option<Extension<User>>) -> Response {
+    let owner = User::owner(&user);
+    state
+        .history
+        .update_and_redirect("/history", |h| h.entries.retain(|e| e.user != owner))
+}
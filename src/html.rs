@@ -1,11 +1,118 @@
-use maud::{html, Markup, DOCTYPE};
+use std::time::SystemTime;
+
+use maud::{html, Markup, PreEscaped, DOCTYPE};
+use url::form_urlencoded::byte_serialize;
+
+use crate::{assets, github, links, profile, security};
+
+/// Pressing `/` outside of a text field jumps to the search box.
+const FOCUS_ON_SLASH: &str = "
+document.addEventListener('keydown', function (e) {
+    const tag = document.activeElement && document.activeElement.tagName;
+    if (e.key === '/' && tag !== 'INPUT' && tag !== 'TEXTAREA') {
+        e.preventDefault();
+        document.querySelector('input[name=\"q\"]').focus();
+    }
+});
+";
+
+/// Where the browser can take server-sent events, searching asks for the
+/// streaming results page. Without JavaScript the form submits as it is.
+const STREAM_ON_SUBMIT: &str = "
+document.querySelectorAll('form[action=\"/\"]').forEach(function (form) {
+    if (!window.EventSource) return;
+    form.addEventListener('submit', function () {
+        const stream = document.createElement('input');
+        stream.type = 'hidden';
+        stream.name = 'stream';
+        stream.value = 'true';
+        form.appendChild(stream);
+    });
+});
+";
+
+/// Typing in the search box on the results page updates the results after a
+/// pause, and the pager swaps pages in place. Both fetch `/results`, which
+/// answers with what goes inside `#results`. Without JavaScript, the form and
+/// the pager links load whole pages.
+///
+/// Every search counts against the rate limit, so typing only searches once
+/// the query is a few characters long, has settled and differs from the one
+/// shown. When the limit is hit anyway, a note under the search box says so.
+const INSTANT_SEARCH: &str = "
+(function () {
+    const MIN_CHARS = 3;
+    const DEBOUNCE_MS = 800;
+    const input = document.querySelector('input[name=\"q\"]');
+    const notice = document.getElementById('search-notice');
+    let shown = new URLSearchParams(location.search).get('q') || '';
+    let controller = null;
+    let timer = null;
+    function load(params, push) {
+        if (controller) controller.abort();
+        controller = new AbortController();
+        return fetch('/results?' + params, { signal: controller.signal })
+            .then(function (resp) {
+                // Rate limiting answers with a whole page; keep what is shown
+                // and say why it didn't change.
+                if (resp.status === 429) {
+                    const wait = resp.headers.get('retry-after');
+                    notice.textContent = 'Searching too quickly. Try again in '
+                        + (wait || 'a few') + ' seconds.';
+                    notice.hidden = false;
+                    return true;
+                }
+                // So does everything else that isn't a fragment, e.g. the
+                // login page once the session has run out. Load it properly.
+                if (resp.redirected || resp.headers.get('x-fragment') !== 'results') {
+                    location.href = '/?' + params;
+                    return true;
+                }
+                return resp.text().then(function (html) {
+                    document.getElementById('results').innerHTML = html;
+                    notice.hidden = true;
+                    shown = params.get('q') || '';
+                    const url = '/?' + params;
+                    if (push) history.pushState(null, '', url);
+                    else history.replaceState(null, '', url);
+                    return resp.ok;
+                });
+            })
+            .catch(function () {
+                return false;
+            });
+    }
+    input.addEventListener('input', function () {
+        clearTimeout(timer);
+        timer = setTimeout(function () {
+            const q = input.value.trim();
+            if (q.length < MIN_CHARS || q === shown) return;
+            load(new URLSearchParams({ q: q }), false);
+        }, DEBOUNCE_MS);
+    });
+    document.addEventListener('click', function (e) {
+        const link = e.target.closest('a[data-fragment]');
+        if (!link || e.ctrlKey || e.metaKey || e.shiftKey || e.button !== 0) return;
+        e.preventDefault();
+        load(new URL(link.href).searchParams, true).then(function (ok) {
+            if (!ok) location.href = link.href;
+        });
+    });
+    window.addEventListener('popstate', function () {
+        location.reload();
+    });
+})();
+";
+
+const SEARCH_ICON: &str = "M21 21l-6-6m2-5a7 7 0 11-14 0 7 7 0 0114 0z";
 
 fn header(page_title: &str) -> Markup {
     html! {
         (DOCTYPE)
         head {
             meta charset="utf-8";
-            link rel="stylesheet" href="static/base.css";
+            meta name="referrer" content="no-referrer";
+            link rel="stylesheet" href=(assets::stylesheet_url());
             title { (page_title) }
         }
     }
@@ -15,24 +122,247 @@ fn footer() -> Markup {
     html! {
         footer {
             a href="https://my-search.net" { "my-search.net" }
+            " · "
+            a href="/history" { "History" }
+            " · "
+            a href="/saved" { "Saved" }
+            " · "
+            a href="/settings" { "Settings" }
         }
     }
 }
 
+/// Inline script allowed by the Content-Security-Policy of the current request.
+pub fn script(js: &str) -> Markup {
+    html! {
+        script nonce=(security::nonce()) { (PreEscaped(js)) }
+    }
+}
+
+/// The theme and density classes come from the current user's profile.
 pub fn page(title: &str, page_contents: Markup) -> Markup {
+    let (theme, density) = profile::appearance();
     html! {
         (header(title))
-        (page_contents)
-        (footer())
+        div class={ "theme-" (theme.as_str()) " density-" (density.as_str()) } {
+            (page_contents)
+            (footer())
+        }
     }
 }
 
-pub fn not_found() -> Markup {
+/// Page with content centered on screen, used for the landing page and error pages.
+pub fn centered(title: &str, width: &str, contents: Markup) -> Markup {
     page(
-        "Not found",
+        title,
+        html! {
+            div class="min-h-screen flex items-center justify-center p-4" {
+                div class={ "w-full mx-auto fade-in " (width) } {
+                    (contents)
+                }
+            }
+        },
+    )
+}
+
+/// Page with a column of content starting at the top, used for results and listings.
+pub fn column(title: &str, contents: Markup) -> Markup {
+    page(
+        title,
         html! {
-            h1 { "404" }
-            p { "Not found" }
+            div class="min-h-screen p-4" {
+                div class="max-w-4xl mx-auto space-y-6" {
+                    (contents)
+                }
+            }
         },
     )
 }
+
+pub fn card(contents: Markup) -> Markup {
+    html! {
+        div class="card rounded-2xl p-6 transition-all duration-300 fade-in" {
+            (contents)
+        }
+    }
+}
+
+pub fn logo() -> Markup {
+    html! {
+        div class="text-center mb-12" {
+            h1 class="text-6xl font-bold logo-text mb-2" {
+                "My Search"
+            }
+            p class="text-slate-400 text-lg" {
+                "Find what you're looking for"
+            }
+        }
+    }
+}
+
+pub enum SearchBox<'a> {
+    /// Large standalone box for the landing page.
+    Hero,
+    /// Single-row bar above results, prefilled with the current query.
+    Compact(&'a str),
+}
+
+pub fn search_box(kind: SearchBox) -> Markup {
+    match kind {
+        SearchBox::Hero => html! {
+            form class="space-y-6" method="get" action="/" {
+                div class="relative search-glow rounded-2xl bg-slate-800/50 backdrop-blur-sm border border-slate-700/50 transition-all duration-300" {
+                    input
+                        type="text"
+                        name="q"
+                        placeholder="Enter your search term..."
+                        class="w-full px-6 py-4 text-lg bg-transparent text-white placeholder-slate-400 focus:outline-none rounded-2xl"
+                        autocomplete="off"
+                        autofocus
+                        required;
+                    div class="absolute right-4 top-1/2 transform -translate-y-1/2" {
+                        svg class="w-6 h-6 text-slate-400" fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                            path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d=(SEARCH_ICON);
+                        }
+                    }
+                }
+
+                div class="text-center" {
+                    button
+                        type="submit"
+                        class="lift-button inline-flex items-center px-8 py-3 bg-blue-800 hover:bg-blue-700 text-white font-semibold rounded-xl transition-all duration-300 focus:outline-none focus:ring-4 focus:ring-blue-800/30" {
+                        svg class="w-5 h-5 mr-2" fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                            path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d=(SEARCH_ICON);
+                        }
+                        "Search"
+                    }
+                }
+            }
+            (script(STREAM_ON_SUBMIT))
+        },
+        SearchBox::Compact(query) => html! {
+            div class="card search-glow rounded-2xl p-6 transition-all duration-300 fade-in" {
+                form class="flex gap-4" method="get" action="/" {
+                    div class="flex-1 relative" {
+                        input
+                            type="text"
+                            name="q"
+                            value=(query)
+                            placeholder="Enter search term..."
+                            class="w-full px-4 py-3 text-lg bg-slate-800/50 text-white placeholder-slate-400 border border-slate-700/50 rounded-xl focus:outline-none focus:border-red-500/50 focus:ring-2 focus:ring-red-500/20 transition-all duration-200";
+                    }
+                    button
+                        type="submit"
+                        class="lift-button px-6 py-3 bg-blue-800 hover:bg-blue-700 text-white text-lg font-semibold rounded-xl transition-all duration-300 focus:outline-none focus:ring-4 focus:ring-blue-800/30" {
+                        "Search"
+                    }
+                }
+                p id="search-notice" class="text-red-400 text-sm mt-2" hidden {}
+            }
+            (script(FOCUS_ON_SLASH))
+            (script(STREAM_ON_SUBMIT))
+            (script(INSTANT_SEARCH))
+        },
+    }
+}
+
+/// A single search result. `clean_links` strips tracking parameters from the URL.
+pub fn result_card(item: &github::Item, clean_links: bool, new_tab: bool) -> Markup {
+    html! {
+        div class="result-item rounded-xl p-4" {
+            h3 class="text-lg font-semibold text-white mb-2" {
+                (item.title)
+            }
+            @match links::outbound(&item.html_url, clean_links) {
+                Some(url) => {
+                    a href=(url) target=[new_tab.then_some("_blank")] rel="noopener noreferrer" class="text-blue-400 hover:text-blue-300 transition-colors duration-200 text-sm break-all" {
+                        (url)
+                    }
+                }
+                None => {
+                    span class="text-slate-500 text-sm" { "Link removed: not an http(s) URL" }
+                }
+            }
+        }
+    }
+}
+
+/// The result cards of one search, or a note that nothing matched.
+pub fn result_list(query: &str, res: &github::Root, clean_links: bool, new_tab: bool) -> Markup {
+    html! {
+        @if res.items.is_empty() {
+            p class="text-center text-slate-400 py-8" {
+                "No results found for \"" (query) "\""
+            }
+        } @else {
+            div class="space-y-4" {
+                @for item in &res.items {
+                    (result_card(item, clean_links, new_tab))
+                }
+            }
+        }
+    }
+}
+
+/// Previous and next links below the results, if there is more than one page.
+pub fn pager(query: &str, page: u32, total_count: i64, per_page: u8) -> Markup {
+    let reachable = total_count.clamp(0, github::MAX_RESULTS) as u32;
+    let last = reachable.div_ceil(per_page.max(1) as u32).max(1);
+    let q = byte_serialize(query.as_bytes()).collect::<String>();
+    let href = |page: u32| format!("/?q={q}&page={page}");
+    html! {
+        @if last > 1 {
+            nav class="flex items-center justify-between mt-6 text-sm" {
+                @if page > 1 {
+                    a href=(href(page - 1)) data-fragment class="text-blue-400 hover:text-blue-300" { "← Previous" }
+                } @else {
+                    span {}
+                }
+                span class="text-slate-400" { "Page " (page) " of " (last) }
+                @if page < last {
+                    a href=(href(page + 1)) data-fragment class="text-blue-400 hover:text-blue-300" { "Next →" }
+                } @else {
+                    span {}
+                }
+            }
+        }
+    }
+}
+
+pub fn error_card(title: &str, message: &str, hint: &str) -> Markup {
+    html! {
+        div class="card search-glow rounded-2xl p-8 transition-all duration-300" {
+            h1 class="text-2xl font-bold text-center mb-4 logo-text" {
+                (title)
+            }
+            p class="text-center text-slate-300 mb-2" {
+                (message)
+            }
+            p class="text-center text-slate-400 text-sm mb-6" {
+                (hint)
+            }
+            a href="/" class="lift-button block w-full py-3 bg-blue-800 hover:bg-blue-700 text-white text-lg font-semibold rounded-xl text-center transition-all duration-300 focus:outline-none focus:ring-4 focus:ring-blue-800/30" {
+                "Try Again"
+            }
+        }
+    }
+}
+
+pub fn not_found() -> Markup {
+    centered(
+        "Not found",
+        "max-w-md",
+        error_card("404", "Not found", "The page you asked for does not exist."),
+    )
+}
+
+/// How long ago `t` was, in the largest whole unit: "42s ago", "3h ago".
+pub fn ago(t: SystemTime) -> String {
+    let secs = t.elapsed().map(|d| d.as_secs()).unwrap_or_default();
+    match secs {
+        0..60 => format!("{secs}s ago"),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
@@ -0,0 +1,542 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use auth::Auth;
+use auth::User;
+use axum::{
+    extract::{Query, State},
+    http::{header, Extensions, HeaderMap, StatusCode, Version},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post, Router},
+    Extension, Json,
+};
+use cache::Cache;
+use config::Config;
+use error::{Error, JsonError, Result};
+use history::History;
+use html::{
+    card, centered, column, error_card, logo, not_found, pager, result_list, search_box, SearchBox,
+};
+use maud::{html, Markup};
+use metrics::{InFlight, METRICS};
+use profile::{Profile, Profiles};
+use ratelimit::{Metered, RateLimiter};
+use reqwest::header::{CONTENT_TYPE, USER_AGENT};
+use serde::Deserialize;
+use store::Store;
+use telemetry::QueryHasher;
+use tower_http::compression::{
+    predicate::{Predicate, SizeAbove},
+    CompressionLayer,
+};
+use tracing::{field, info, instrument, warn, Span};
+use url::form_urlencoded::byte_serialize;
+use watch::Watches;
+
+mod assets;
+pub mod auth;
+mod bang;
+mod cache;
+pub mod config;
+mod error;
+mod export;
+mod feed;
+mod github;
+mod history;
+mod html;
+mod links;
+pub mod listen;
+mod metrics;
+mod profile;
+mod ratelimit;
+mod security;
+pub mod shutdown;
+mod status;
+mod store;
+mod stream;
+pub mod telemetry;
+pub mod tls;
+pub mod watch;
+mod webhook;
+
+async fn not_found_handler() -> (StatusCode, Markup) {
+    (StatusCode::NOT_FOUND, not_found())
+}
+
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Arc<Config>,
+    auth: Arc<Auth>,
+    profiles: Arc<Profiles>,
+    history: Arc<Store<History>>,
+    watches: Arc<Store<Watches>>,
+    feed_cache: Arc<Cache<github::Root>>,
+    limiter: Arc<RateLimiter>,
+    client: reqwest::Client,
+    /// Doesn't follow redirects, which could lead a webhook past the address check.
+    /// Only used when private receivers are allowed; otherwise each delivery
+    /// gets a client pinned to the addresses it checked.
+    webhook_client: reqwest::Client,
+    /// Saved searches with a test delivery under way; each may have only one.
+    test_deliveries: Arc<Mutex<HashSet<u64>>>,
+    query_hasher: QueryHasher,
+}
+
+impl AppState {
+    pub fn new(config: Config) -> Self {
+        let auth = Auth::from_config(&config);
+        AppState {
+            query_hasher: QueryHasher::new(auth.key()),
+            auth: Arc::new(auth),
+            profiles: Arc::new(Profiles::open(config.data_dir.as_deref(), "profiles.json")),
+            history: Arc::new(Store::open(config.data_dir.as_deref(), "history.json")),
+            watches: Arc::new(Store::open(config.data_dir.as_deref(), "saved.json")),
+            feed_cache: Arc::new(Cache::new(feed::FEED_TTL)),
+            limiter: Arc::new(RateLimiter::from_config(&config)),
+            config: Arc::new(config),
+            client: reqwest::Client::new(),
+            webhook_client: reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .expect("TLS backend"),
+            test_deliveries: Arc::default(),
+        }
+    }
+
+    /// Writes out state whose saving is deferred. Call once the server has
+    /// stopped taking requests.
+    pub fn flush(&self) {
+        self.history.flush();
+        self.watches.flush();
+    }
+}
+
+pub fn app(state: AppState) -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(root).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                ratelimit::html,
+            )),
+        )
+        .route(
+            "/api/search",
+            get(api_search).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                ratelimit::api,
+            )),
+        )
+        .route(
+            "/feed",
+            get(feed::feed).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                ratelimit::html,
+            )),
+        )
+        .route(
+            "/export",
+            get(export::download).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                ratelimit::html,
+            )),
+        )
+        .route(
+            "/results",
+            get(results_fragment).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                ratelimit::html,
+            )),
+        )
+        .route(
+            "/stream",
+            get(stream::results).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                ratelimit::html,
+            )),
+        )
+        .route("/static/{*file}", get(assets::handler))
+        .route("/metrics", get(metrics::metrics_handler))
+        .route("/healthz", get(status::healthz))
+        .route("/readyz", get(status::readyz))
+        .route("/status", get(status::status_page))
+        .route("/login", get(auth::login_page).post(auth::login))
+        .route("/logout", get(auth::logout))
+        .route(
+            "/settings",
+            get(profile::settings_page).post(profile::save_settings),
+        )
+        .route("/settings/reset", post(profile::reset_settings))
+        .route("/history", get(history::history_page))
+        .route("/history/{id}/delete", post(history::delete_entry))
+        .route("/history/clear", post(history::clear))
+        .route("/saved", get(watch::dashboard))
+        .route(
+            "/saved",
+            post(watch::create).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                ratelimit::actions,
+            )),
+        )
+        .route(
+            "/saved/{id}/run",
+            post(watch::run_now).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                ratelimit::actions,
+            )),
+        )
+        .route("/saved/{id}/seen", post(watch::mark_seen))
+        .route("/saved/{id}/delete", post(watch::delete))
+        .route("/saved/{id}/webhook", post(watch::set_webhook))
+        .route("/saved/{id}/webhook/test", post(watch::test_webhook))
+        .fallback_service(get(not_found_handler))
+        .layer(middleware::from_fn_with_state(state.clone(), profile::load))
+        .layer(middleware::from_fn_with_state(state.clone(), auth::require))
+        .layer(middleware::from_fn(security::headers))
+        .layer(compression())
+        .layer(middleware::from_fn(metrics::track_http))
+        .layer(telemetry::propagate_request_id())
+        .layer(telemetry::trace())
+        .layer(telemetry::set_request_id())
+        .with_state(state)
+}
+
+/// gzip/brotli for text responses. Embedded images and other binaries are skipped.
+fn compression() -> CompressionLayer<impl Predicate> {
+    let textual = |_: StatusCode, _: Version, headers: &HeaderMap, _: &Extensions| {
+        headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|ct| ct.to_str().ok())
+            .is_some_and(|ct| {
+                [
+                    "text/html",
+                    "text/css",
+                    "text/csv",
+                    "text/markdown",
+                    "application/json",
+                ]
+                .iter()
+                .any(|t| ct.starts_with(t))
+            })
+    };
+    CompressionLayer::new()
+        .no_deflate()
+        .no_zstd()
+        .compress_when(SizeAbove::default().and(textual))
+}
+
+#[derive(Deserialize)]
+struct Search {
+    q: Option<String>,
+    /// Set by the search form's script where results can be streamed.
+    #[serde(default)]
+    stream: bool,
+    /// Counting from 1.
+    page: Option<u32>,
+}
+
+impl Search {
+    fn page(&self) -> u32 {
+        self.page.unwrap_or(1).max(1)
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiSearch {
+    q: Option<String>,
+    /// `json` (the default), or one of the export formats.
+    format: Option<String>,
+}
+
+async fn root(
+    State(state): State<AppState>,
+    profile: Profile,
+    user: Option<Extension<User>>,
+    search: Query<Search>,
+) -> Result<Markup> {
+    let query = match &search.q {
+        None => {
+            return Ok(centered(
+                "My Search",
+                "max-w-2xl",
+                html! {
+                    (logo())
+                    (search_box(SearchBox::Hero))
+                },
+            ));
+        }
+        Some(q) => q,
+    };
+
+    // Only the page around the results; they arrive from `/stream`.
+    if search.stream {
+        return Ok(results_page(
+            query,
+            results_panel(query, None, stream::placeholder(query)),
+        ));
+    }
+
+    let page = search.page();
+    let res = search_page(&state, &profile, query, page, profile.per_page).await?;
+    // Paging through is still the same search.
+    if page == 1 {
+        let user = user.as_ref().map(|Extension(u)| u);
+        history::record(&state, user, &profile, query, res.total_count);
+    }
+
+    Ok(results_page(
+        query,
+        results_panel(
+            query,
+            Some(res.total_count),
+            listing(&state, &profile, query, page, &res),
+        ),
+    ))
+}
+
+/// `/results?q=...&page=...`: only what goes inside `#results`, for swapping
+/// into a page that is already open. Errors are fragments too, and all of them
+/// carry `X-Fragment` so the page can tell them from whole pages, such as the
+/// login page after a session runs out. Nothing is recorded in history, since
+/// search-as-you-type asks on every pause in typing.
+async fn results_fragment(
+    State(state): State<AppState>,
+    profile: Profile,
+    search: Query<Search>,
+) -> Response {
+    let query = search.q.as_deref().unwrap_or_default();
+    let page = search.page();
+    let (status, panel) = match search_page(&state, &profile, query, page, profile.per_page).await {
+        Ok(res) => (
+            StatusCode::OK,
+            results_panel(
+                query,
+                Some(res.total_count),
+                listing(&state, &profile, query, page, &res),
+            ),
+        ),
+        Err(e) => {
+            let card = error_card(e.title(), &e.to_string(), &e.hint());
+            (e.status(), results_panel(query, None, card))
+        }
+    };
+    (status, [("x-fragment", "results")], panel).into_response()
+}
+
+fn listing(
+    state: &AppState,
+    profile: &Profile,
+    query: &str,
+    page: u32,
+    res: &github::Root,
+) -> Markup {
+    html! {
+        (result_list(query, res, state.config.clean_links, profile.new_tab))
+        (pager(query, page, res.total_count, profile.per_page))
+    }
+}
+
+fn results_page(query: &str, panel: Markup) -> Markup {
+    column(
+        "Search Results",
+        html! {
+            (search_box(SearchBox::Compact(query)))
+            (card(html! {
+                div id="results" { (panel) }
+            }))
+        },
+    )
+}
+
+/// The results card's contents: the header with the count, when known, and
+/// the actions for this query, then `results`.
+fn results_panel(query: &str, total_count: Option<i64>, results: Markup) -> Markup {
+    let q = byte_serialize(query.as_bytes()).collect::<String>();
+    html! {
+        div class="flex items-center mb-4" {
+            h2 class="flex-1 text-2xl font-bold text-white" {
+                "Results"
+                @if let Some(n) = total_count { " (" (n) ")" }
+            }
+            a href={ "/feed?q=" (q) }
+                class="text-sm text-blue-400 hover:text-blue-300 mr-4" { "Feed" }
+            details class="relative text-sm mr-4" {
+                summary class="cursor-pointer text-blue-400 hover:text-blue-300" { "Export" }
+                div class="absolute right-0 mt-2 p-2 bg-slate-800 rounded-xl flex flex-col gap-1 whitespace-nowrap z-10" {
+                    @for format in export::Format::ALL {
+                        a href={ "/export?q=" (q) "&format=" (format.as_str()) }
+                            class="text-blue-400 hover:text-blue-300" { (format.label()) }
+                    }
+                }
+            }
+            (watch::watch_button(query))
+        }
+
+        (results)
+    }
+}
+
+async fn api_search(
+    State(state): State<AppState>,
+    profile: Profile,
+    metered: Option<Extension<Metered>>,
+    Query(search): Query<ApiSearch>,
+) -> Result<Response, JsonError> {
+    let query = search.q.unwrap_or_default();
+    match search.format.as_deref() {
+        None | Some("json") => {
+            Ok(Json(run_search(&state, &profile, &query).await?).into_response())
+        }
+        Some(format) => {
+            let format = export::Format::parse(format)?;
+            let metered = metered.map(|Extension(m)| m);
+            Ok(export::stream_results(state, profile, metered, query, format).await?)
+        }
+    }
+}
+
+async fn run_search(state: &AppState, profile: &Profile, query: &str) -> Result<github::Root> {
+    search_page(state, profile, query, 1, profile.per_page).await
+}
+
+/// One page of results, `page` counting from 1.
+#[instrument(name = "search", skip_all, fields(query_hash = field::Empty, page = page, result_count = field::Empty))]
+async fn search_page(
+    state: &AppState,
+    profile: &Profile,
+    query: &str,
+    page: u32,
+    per_page: u8,
+) -> Result<github::Root> {
+    fetch_page(state, profile, query, page, per_page).await
+}
+
+/// [`search_page`] without a span of its own, for callers that open their
+/// own `search` span.
+async fn fetch_page(
+    state: &AppState,
+    profile: &Profile,
+    query: &str,
+    page: u32,
+    per_page: u8,
+) -> Result<github::Root> {
+    Span::current().record("query_hash", state.query_hasher.hash(query));
+    let _in_flight = InFlight::start();
+
+    let (provider, query) = profile.route(query);
+    if query.is_empty() {
+        return Err(Error::BadQuery("the search query is empty".into()));
+    }
+    // GitHub is the only provider so far; routing only decides whether it runs.
+    if provider.is_none() {
+        return Err(Error::BadQuery(
+            "every search provider is turned off in your settings".into(),
+        ));
+    }
+    let token = profile
+        .tokens
+        .get("github")
+        .or(state.config.github_token.as_ref());
+
+    let res = get_github(state, &query, page, per_page, token.map(String::as_str))
+        .await
+        .inspect_err(|e| warn!(error = %e, "search failed"))?;
+    Span::current().record("result_count", res.items.len());
+    Ok(res)
+}
+
+#[instrument(
+    name = "provider",
+    skip_all,
+    fields(provider = "github", status = field::Empty, latency_ms = field::Empty, result_count = field::Empty)
+)]
+async fn get_github(
+    state: &AppState,
+    q: &str,
+    page: u32,
+    per_page: u8,
+    token: Option<&str>,
+) -> Result<github::Root> {
+    let started = Instant::now();
+    let res = tokio::select! {
+        res = fetch_github(state, q, page, per_page, token) => res,
+        _ = shutdown::ABORT.cancelled() => Err(Error::Cancelled),
+    };
+    let elapsed = started.elapsed();
+    let shared = token == state.config.github_token.as_deref();
+    status::record("github", elapsed, &res, shared);
+
+    Span::current().record("latency_ms", elapsed.as_millis() as u64);
+    METRICS
+        .provider_duration
+        .with_label_values(&["github"])
+        .observe(elapsed.as_secs_f64());
+    let outcome = match &res {
+        Ok(resp) => {
+            Span::current().record("result_count", resp.items.len());
+            "ok"
+        }
+        Err(e) => e.kind(),
+    };
+    METRICS
+        .provider_requests
+        .with_label_values(&["github", outcome])
+        .inc();
+    info!(outcome, "provider call finished");
+    res
+}
+
+async fn fetch_github(
+    state: &AppState,
+    q: &str,
+    page: u32,
+    per_page: u8,
+    token: Option<&str>,
+) -> Result<github::Root> {
+    // https://api.stackexchange.com/2.3/search/advanced?order=desc&sort=activity&site=stackoverflow&q=js%20fibonacci
+    // https://api.github.com/search/issues?q=box%20error
+    let mut req = state
+        .client
+        .get(format!("{}/search/issues", state.config.github_api_url))
+        .query(&[
+            ("q", q),
+            ("page", &page.to_string()),
+            ("per_page", &per_page.to_string()),
+        ])
+        .header(USER_AGENT, "my-search")
+        .timeout(Duration::from_secs(10));
+    if let Some(token) = token {
+        req = req.bearer_auth(token);
+    }
+    let get_resp = req.send().await?;
+
+    let status = get_resp.status();
+    Span::current().record("status", status.as_u16());
+    if let Some(remaining) = github::rate_limit_remaining(get_resp.headers()) {
+        METRICS
+            .rate_limit_remaining
+            .with_label_values(&["github"])
+            .set(remaining);
+        status::record_rate_limit(
+            "github",
+            remaining,
+            github::rate_limit_reset(get_resp.headers()),
+        );
+    }
+    if !status.is_success() {
+        return Err(github::status_error(status, get_resp.headers()));
+    }
+
+    if let Some(ct) = get_resp.headers().get(CONTENT_TYPE) {
+        let ct = ct.to_str().unwrap_or_default();
+        if !ct.starts_with("application/json") {
+            return Err(Error::Decode(format!("expected JSON, got {ct}")));
+        }
+    }
+
+    Ok(get_resp.json::<github::Root>().await?)
+}
@@ -0,0 +1,36 @@
+use url::Url;
+
+/// Query parameters that only exist to track clicks.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid", "igshid", "yclid", "_hsenc",
+    "_hsmi", "ref_src",
+];
+
+fn is_tracking(name: &str) -> bool {
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name)
+}
+
+/// Validates a URL taken from upstream data before it is rendered as a link.
+/// Anything that is not plain http(s), e.g. `javascript:` or `data:`, is rejected.
+/// With `clean`, tracking parameters are stripped.
+pub fn outbound(raw: &str, clean: bool) -> Option<String> {
+    let mut url = Url::parse(raw.trim()).ok()?;
+    if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+        return None;
+    }
+
+    if clean && url.query().is_some() {
+        let kept: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(name, _)| !is_tracking(name))
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect();
+        if kept.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(kept);
+        }
+    }
+
+    Some(url.into())
+}
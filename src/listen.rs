@@ -0,0 +1,93 @@
+use std::{
+    env, fs, io,
+    net::SocketAddr,
+    os::unix::{
+        fs::{FileTypeExt, PermissionsExt},
+        io::{FromRawFd, RawFd},
+        net as unix,
+    },
+    path::PathBuf,
+};
+
+use tracing::{info, warn};
+
+use crate::config::Config;
+
+/// First file descriptor passed by systemd socket activation, `SD_LISTEN_FDS_START`.
+const LISTEN_FDS_START: RawFd = 3;
+
+pub enum Listener {
+    Tcp(std::net::TcpListener),
+    /// Bound socket, and its path when we created it ourselves and should remove it on exit.
+    Unix(unix::UnixListener, Option<PathBuf>),
+}
+
+/// Picks the listener in order of precedence: a socket passed in by systemd
+/// (`LISTEN_FDS`), the configured Unix socket, then TCP on the configured port.
+pub fn bind(config: &Config) -> io::Result<Listener> {
+    if let Some(listener) = from_systemd()? {
+        return Ok(listener);
+    }
+
+    if let Some(path) = &config.unix_socket {
+        // A socket left behind by an unclean exit would make bind fail. Anything
+        // else at that path is not ours to delete.
+        match fs::symlink_metadata(path) {
+            Ok(meta) if meta.file_type().is_socket() => fs::remove_file(path)?,
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                ))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        let listener = unix::UnixListener::bind(path)?;
+        fs::set_permissions(path, fs::Permissions::from_mode(config.unix_socket_mode))?;
+        listener.set_nonblocking(true)?;
+        info!(path = %path.display(), mode = format!("{:o}", config.unix_socket_mode), "Listening");
+        return Ok(Listener::Unix(listener, Some(path.clone())));
+    }
+
+    let listener = std::net::TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], config.port)))?;
+    listener.set_nonblocking(true)?;
+    info!(port = config.port, "Listening");
+    Ok(Listener::Tcp(listener))
+}
+
+/// Takes over the listening socket handed to us by systemd, if any. See sd_listen_fds(3).
+fn from_systemd() -> io::Result<Option<Listener>> {
+    let for_us = env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .is_some_and(|pid| pid == std::process::id());
+    let count = env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|n| n.parse::<RawFd>().ok())
+        .unwrap_or(0);
+    if !for_us || count == 0 {
+        return Ok(None);
+    }
+    if count > 1 {
+        warn!(count, "only the first socket passed by systemd is used");
+    }
+
+    // SAFETY: systemd guarantees LISTEN_FDS descriptors starting at 3 are open
+    // and ours, and nothing else in the process has claimed them yet.
+    let fd = LISTEN_FDS_START;
+    let as_unix = unsafe { unix::UnixListener::from_raw_fd(fd) };
+    let listener = if as_unix.local_addr().is_ok() {
+        as_unix.set_nonblocking(true)?;
+        info!(fd, "Listening on systemd Unix socket");
+        Listener::Unix(as_unix, None)
+    } else {
+        // Not a Unix socket: hand the same descriptor over to a TCP listener instead.
+        let fd = std::os::unix::io::IntoRawFd::into_raw_fd(as_unix);
+        let as_tcp = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+        as_tcp.set_nonblocking(true)?;
+        info!(fd, addr = ?as_tcp.local_addr()?, "Listening on systemd socket");
+        Listener::Tcp(as_tcp)
+    };
+    Ok(Some(listener))
+}
@@ -0,0 +1,169 @@
+use std::{sync::LazyLock, time::Instant};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests: IntCounterVec,
+    pub http_duration: HistogramVec,
+    pub provider_requests: IntCounterVec,
+    pub provider_duration: HistogramVec,
+    pub rate_limit_remaining: IntGaugeVec,
+    pub searches_in_flight: IntGauge,
+    pub rate_limited: IntCounterVec,
+    pub cache_lookups: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("my_search".into()), None).unwrap();
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status"),
+            &["route", "status"],
+        )
+        .unwrap();
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency by route",
+            ),
+            &["route"],
+        )
+        .unwrap();
+        let provider_requests = IntCounterVec::new(
+            Opts::new(
+                "provider_requests_total",
+                "Search provider calls by provider and outcome",
+            ),
+            &["provider", "outcome"],
+        )
+        .unwrap();
+        let provider_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "provider_request_duration_seconds",
+                "Search provider call latency",
+            ),
+            &["provider"],
+        )
+        .unwrap();
+        let rate_limit_remaining = IntGaugeVec::new(
+            Opts::new(
+                "provider_rate_limit_remaining",
+                "Requests left in the current provider rate-limit window",
+            ),
+            &["provider"],
+        )
+        .unwrap();
+        let searches_in_flight =
+            IntGauge::new("searches_in_flight", "Searches currently being served").unwrap();
+        let rate_limited = IntCounterVec::new(
+            Opts::new(
+                "rate_limited_total",
+                "Searches rejected by the inbound rate limiter, by route class",
+            ),
+            &["class"],
+        )
+        .unwrap();
+        let cache_lookups = IntCounterVec::new(
+            Opts::new(
+                "cache_lookups_total",
+                "Result cache lookups by cache and outcome (hit or miss)",
+            ),
+            &["cache", "outcome"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(http_duration.clone())).unwrap();
+        registry
+            .register(Box::new(provider_requests.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(provider_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(rate_limit_remaining.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(searches_in_flight.clone()))
+            .unwrap();
+        registry.register(Box::new(rate_limited.clone())).unwrap();
+        registry.register(Box::new(cache_lookups.clone())).unwrap();
+
+        Metrics {
+            registry,
+            http_requests,
+            http_duration,
+            provider_requests,
+            provider_duration,
+            rate_limit_remaining,
+            searches_in_flight,
+            rate_limited,
+            cache_lookups,
+        }
+    }
+}
+
+/// Decrements `searches_in_flight` when dropped, so early returns are counted correctly.
+pub struct InFlight;
+
+impl InFlight {
+    pub fn start() -> Self {
+        METRICS.searches_in_flight.inc();
+        InFlight
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        METRICS.searches_in_flight.dec();
+    }
+}
+
+/// Records request count and latency labelled by the matched route, not the raw path,
+/// so arbitrary URLs can't blow up label cardinality.
+pub async fn track_http(req: Request, next: Next) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_owned())
+        .unwrap_or_else(|| "unmatched".into());
+    let started = Instant::now();
+
+    let resp = next.run(req).await;
+
+    METRICS
+        .http_duration
+        .with_label_values(&[&route])
+        .observe(started.elapsed().as_secs_f64());
+    METRICS
+        .http_requests
+        .with_label_values(&[&route, resp.status().as_str()])
+        .inc();
+    resp
+}
+
+pub async fn metrics_handler() -> Response {
+    let encoder = TextEncoder::new();
+    let mut buf = Vec::new();
+    if let Err(e) = encoder.encode(&METRICS.registry.gather(), &mut buf) {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+    (
+        [(header::CONTENT_TYPE, encoder.format_type().to_owned())],
+        buf,
+    )
+        .into_response()
+}
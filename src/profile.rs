@@ -0,0 +1,425 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    convert::Infallible,
+    time::Duration,
+};
+
+use axum::{
+    extract::{FromRef, FromRequestParts, Request, State},
+    http::{request::Parts, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+    Extension, Form,
+};
+use axum_extra::extract::{
+    cookie::{Cookie, Key, SameSite},
+    SignedCookieJar,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64, Engine};
+use maud::{html, Markup};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::{
+    auth::User,
+    bang::{self, BANGS},
+    html::{card, centered, column, error_card},
+    security,
+    status::PROVIDERS,
+    store::Store,
+    AppState,
+};
+
+/// Preferences of anonymous visitors, signed so they can't be forged into
+/// someone else's. Signed with the session key, so they only survive restarts
+/// when `MY_SEARCH_SECRET` is set.
+const PREFS_COOKIE: &str = "my_search_prefs";
+const PREFS_TTL: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// Choices offered for results per page; GitHub allows up to 100.
+pub const PER_PAGE: &[u8] = &[10, 30, 50, 100];
+
+pub type Profiles = Store<HashMap<String, Profile>>;
+
+tokio::task_local! {
+    static APPEARANCE: (Theme, Density);
+}
+
+/// One user's search preferences. Signed-in users' profiles are kept in the
+/// profile store, anonymous visitors' in a cookie; without either
+/// [`Profile::default`], the instance defaults, apply.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// Providers searched when the query has no bang.
+    pub providers: BTreeSet<String>,
+    /// Bang applied to queries that don't name one, e.g. `gh`.
+    pub default_bang: Option<String>,
+    pub density: Density,
+    pub theme: Theme,
+    /// Results requested from each provider.
+    pub per_page: u8,
+    /// Open result links in a new tab.
+    pub new_tab: bool,
+    /// Keep searches on the `/history` page.
+    pub record_history: bool,
+    /// API tokens by provider, used instead of the instance's own token. Stored
+    /// in plain text in the data directory. Never set for anonymous visitors.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tokens: BTreeMap<String, String>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
+            providers: PROVIDERS.iter().map(|p| p.to_string()).collect(),
+            default_bang: None,
+            density: Density::default(),
+            theme: Theme::default(),
+            per_page: 30,
+            new_tab: true,
+            record_history: true,
+            tokens: BTreeMap::new(),
+        }
+    }
+}
+
+impl Profile {
+    /// The provider `query` should go to and the query with its bang removed.
+    /// `None` when the query has no bang and every provider is turned off.
+    pub fn route(&self, query: &str) -> (Option<&'static str>, String) {
+        let (bang, terms) = bang::split(query);
+        let provider = bang
+            .or_else(|| self.default_bang.as_deref().and_then(bang::provider))
+            .or_else(|| {
+                PROVIDERS
+                    .iter()
+                    .copied()
+                    .find(|p| self.providers.contains(*p))
+            });
+        (provider, terms)
+    }
+
+    /// Applies the settings form. Fields the form leaves out keep their value,
+    /// except the checkboxes, whose absence means "off".
+    fn apply(&mut self, form: Vec<(String, String)>, with_tokens: bool) {
+        self.providers.clear();
+        self.default_bang = None;
+        self.new_tab = false;
+        self.record_history = false;
+        for (name, value) in form {
+            match (name.as_str(), value.trim()) {
+                ("provider", p) if PROVIDERS.contains(&p) => {
+                    self.providers.insert(p.into());
+                }
+                ("default_bang", b) if bang::provider(b).is_some() => {
+                    self.default_bang = Some(b.trim_start_matches('!').to_lowercase());
+                }
+                ("density", "compact") => self.density = Density::Compact,
+                ("density", "comfortable") => self.density = Density::Comfortable,
+                ("theme", "light") => self.theme = Theme::Light,
+                ("theme", "dark") => self.theme = Theme::Dark,
+                ("per_page", n) => {
+                    if let Some(n) = n.parse().ok().filter(|n| PER_PAGE.contains(n)) {
+                        self.per_page = n;
+                    }
+                }
+                ("new_tab", _) => self.new_tab = true,
+                ("record_history", _) => self.record_history = true,
+                ("clear_token", p) if with_tokens => {
+                    self.tokens.remove(p);
+                }
+                (field, token) if with_tokens && !token.is_empty() => {
+                    if let Some(p) = field.strip_prefix("token_") {
+                        if PROVIDERS.contains(&p) {
+                            self.tokens.insert(p.into(), token.into());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Reads an anonymous visitor's profile from the signed cookie.
+    fn from_cookie(jar: &SignedCookieJar) -> Option<Profile> {
+        let cookie = jar.get(PREFS_COOKIE)?;
+        let json = BASE64.decode(cookie.value()).ok()?;
+        let mut profile: Profile = serde_json::from_slice(&json).ok()?;
+        profile.tokens.clear();
+        Some(profile)
+    }
+
+    fn to_cookie(&self, secure: bool) -> Cookie<'static> {
+        let json = serde_json::to_vec(self).unwrap();
+        Cookie::build((PREFS_COOKIE, BASE64.encode(json)))
+            .path("/")
+            .http_only(true)
+            .secure(secure)
+            .same_site(SameSite::Lax)
+            .max_age(PREFS_TTL.try_into().unwrap())
+            .build()
+    }
+
+    /// The profile for a request: the signed-in user's stored profile, else the
+    /// preferences cookie, else the instance defaults.
+    fn resolve(parts: &Parts, state: &AppState) -> Profile {
+        if let Some(user) = parts.extensions.get::<User>() {
+            return state
+                .profiles
+                .read(|all| all.get(&user.name).cloned())
+                .unwrap_or_default();
+        }
+        let jar = SignedCookieJar::from_headers(&parts.headers, Key::from_ref(state));
+        Profile::from_cookie(&jar).unwrap_or_default()
+    }
+}
+
+/// Handlers take the profile as an extractor; [`load`] has usually resolved
+/// it already.
+impl FromRequestParts<AppState> for Profile {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Infallible> {
+        Ok(match parts.extensions.get::<Profile>() {
+            Some(profile) => profile.clone(),
+            None => Profile::resolve(parts, state),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Density {
+    #[default]
+    Comfortable,
+    Compact,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Density {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Density::Comfortable => "comfortable",
+            Density::Compact => "compact",
+        }
+    }
+}
+
+impl Theme {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+        }
+    }
+}
+
+/// Theme and density of the request being rendered, see `html::page`.
+pub fn appearance() -> (Theme, Density) {
+    APPEARANCE.try_with(|a| *a).unwrap_or_default()
+}
+
+/// Resolves the request's [`Profile`] once, adds it to the request extensions
+/// and makes its appearance available to rendering.
+pub async fn load(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let (mut parts, body) = req.into_parts();
+    let profile = Profile::resolve(&parts, &state);
+    let appearance = (profile.theme, profile.density);
+    parts.extensions.insert(profile);
+    APPEARANCE
+        .scope(appearance, next.run(Request::from_parts(parts, body)))
+        .await
+}
+
+pub async fn settings_page(profile: Profile, user: Option<Extension<User>>) -> Markup {
+    settings(&profile, user.is_some())
+}
+
+/// Saves the settings form, to the profile store for signed-in users and to the
+/// preferences cookie otherwise. The form is read as pairs because checkboxes
+/// repeat the `provider` field once per enabled provider.
+pub async fn save_settings(
+    State(state): State<AppState>,
+    mut profile: Profile,
+    user: Option<Extension<User>>,
+    jar: SignedCookieJar,
+    parts: Parts,
+    Form(form): Form<Vec<(String, String)>>,
+) -> Response {
+    let Some(Extension(user)) = user else {
+        profile.apply(form, false);
+        let cookie = profile.to_cookie(security::over_tls(&parts));
+        return (jar.add(cookie), Redirect::to("/settings")).into_response();
+    };
+
+    profile.apply(form, true);
+    let saved = state.profiles.update(|all| {
+        all.insert(user.name.clone(), profile);
+    });
+    if let Err(e) = saved {
+        error!(error = %e, "could not save profiles");
+        return not_saved();
+    }
+    info!(user = user.name, "settings saved");
+    Redirect::to("/settings").into_response()
+}
+
+/// Forgets the stored profile or preferences cookie.
+pub async fn reset_settings(
+    State(state): State<AppState>,
+    user: Option<Extension<User>>,
+    jar: SignedCookieJar,
+) -> Response {
+    let jar = jar.remove(Cookie::build(PREFS_COOKIE).path("/"));
+    if let Some(Extension(user)) = user {
+        if let Err(e) = state.profiles.update(|all| all.remove(&user.name)) {
+            error!(error = %e, "could not save profiles");
+            return not_saved();
+        }
+        info!(user = user.name, "settings reset");
+    }
+    (jar, Redirect::to("/settings")).into_response()
+}
+
+fn not_saved() -> Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        centered(
+            "Settings Not Saved",
+            "max-w-md",
+            error_card(
+                "Settings Not Saved",
+                "Your settings could not be written to disk.",
+                "They apply until the server restarts. Ask the administrator to check the data directory.",
+            ),
+        ),
+    )
+        .into_response()
+}
+
+fn settings(profile: &Profile, signed_in: bool) -> Markup {
+    column(
+        "Settings",
+        html! {
+            h1 class="text-3xl font-bold text-white" { "Settings" }
+            @if !signed_in {
+                (card(html! {
+                    p class="text-slate-300" {
+                        "Your settings are kept in a cookie in this browser."
+                    }
+                }))
+            }
+            (card(html! {
+                form class="space-y-6 text-slate-300" method="post" action="/settings" {
+                    div class="space-y-6" {
+                        div {
+                            h2 class="text-xl font-semibold text-white mb-2" { "Providers" }
+                            @for p in PROVIDERS {
+                                label class="block" {
+                                    input type="checkbox" name="provider" value=(p)
+                                        checked[profile.providers.contains(*p)];
+                                    " " (p)
+                                }
+                            }
+                        }
+                        div {
+                            h2 class="text-xl font-semibold text-white mb-2" { "Default bang" }
+                            p class="text-sm text-slate-400 mb-2" {
+                                "Used when a search doesn't start with one, e.g. "
+                                code { "!gh box error" } "."
+                            }
+                            select name="default_bang" class="bg-slate-800/50 rounded-xl px-4 py-2" {
+                                option value="" selected[profile.default_bang.is_none()] { "None" }
+                                @for (name, provider) in BANGS {
+                                    option value=(name) selected[profile.default_bang.as_deref() == Some(*name)] {
+                                        "!" (name) " (" (provider) ")"
+                                    }
+                                }
+                            }
+                        }
+                        div {
+                            h2 class="text-xl font-semibold text-white mb-2" { "Results" }
+                            label class="block" {
+                                "Results per page "
+                                select name="per_page" class="bg-slate-800/50 rounded-xl px-4 py-2" {
+                                    @for n in PER_PAGE {
+                                        option value=(n) selected[profile.per_page == *n] { (n) }
+                                    }
+                                }
+                            }
+                            label class="block" {
+                                input type="checkbox" name="new_tab" value="on" checked[profile.new_tab];
+                                " Open results in a new tab"
+                            }
+                        }
+                        div {
+                            h2 class="text-xl font-semibold text-white mb-2" { "Appearance" }
+                            @for density in [Density::Comfortable, Density::Compact] {
+                                label class="mr-4" {
+                                    input type="radio" name="density" value=(density.as_str())
+                                        checked[profile.density == density];
+                                    " " (density.as_str())
+                                }
+                            }
+                            br;
+                            @for theme in [Theme::Dark, Theme::Light] {
+                                label class="mr-4" {
+                                    input type="radio" name="theme" value=(theme.as_str())
+                                        checked[profile.theme == theme];
+                                    " " (theme.as_str())
+                                }
+                            }
+                        }
+                        div {
+                            h2 class="text-xl font-semibold text-white mb-2" { "Privacy" }
+                            label class="block" {
+                                input type="checkbox" name="record_history" value="on" checked[profile.record_history];
+                                " Keep a history of my searches"
+                            }
+                        }
+                        @if signed_in {
+                            div {
+                                h2 class="text-xl font-semibold text-white mb-2" { "Provider tokens" }
+                                p class="text-sm text-slate-400 mb-2" {
+                                    "Searches use your token instead of the instance's. Leave empty to keep the current one."
+                                }
+                                @for p in PROVIDERS {
+                                    label class="block mb-2" {
+                                        (p) " "
+                                        input type="password" name={ "token_" (p) } autocomplete="off"
+                                            placeholder=(if profile.tokens.contains_key(*p) { "saved" } else { "not set" })
+                                            class="bg-slate-800/50 rounded-xl px-4 py-2";
+                                    }
+                                    @if profile.tokens.contains_key(*p) {
+                                        label class="block text-sm" {
+                                            input type="checkbox" name="clear_token" value=(p);
+                                            " forget the " (p) " token"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        button
+                            type="submit"
+                            class="lift-button px-6 py-3 bg-blue-800 hover:bg-blue-700 text-white text-lg font-semibold rounded-xl transition-all duration-300" {
+                            "Save"
+                        }
+                    }
+                }
+                form class="mt-4" method="post" action="/settings/reset" {
+                    button type="submit" class="text-sm text-slate-400 hover:text-slate-300 underline" {
+                        "Reset to defaults"
+                    }
+                }
+            }))
+        },
+    )
+}
@@ -0,0 +1,200 @@
+use std::{collections::HashMap, net::SocketAddr, sync::Mutex, time::Instant};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::request::Parts,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::info;
+use url::form_urlencoded;
+
+use crate::{
+    auth::User,
+    config::{Config, Rate},
+    error::{Error, JsonError},
+    metrics::METRICS,
+    AppState,
+};
+
+/// Above this many tracked clients, buckets that have refilled completely are
+/// dropped; they behave exactly like a fresh bucket.
+const PRUNE_AT: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Class {
+    Html,
+    Api,
+}
+
+impl Class {
+    fn as_str(self) -> &'static str {
+        match self {
+            Class::Html => "html",
+            Class::Api => "api",
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refilled(&self, rate: Rate, now: Instant) -> f64 {
+        let added = (now - self.updated).as_secs_f64() * rate.per_sec();
+        (self.tokens + added).min(rate.burst as f64)
+    }
+}
+
+/// Token buckets per client and route class. Every search takes one token, and
+/// so does every further page an export fetches; a bucket holds up to `rate.burst` tokens and refills evenly over `rate.per`.
+pub struct RateLimiter {
+    html: Option<Rate>,
+    api: Option<Rate>,
+    trust_forwarded_for: bool,
+    buckets: Mutex<HashMap<(Class, String), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn from_config(config: &Config) -> Self {
+        RateLimiter {
+            html: config.search_rate_limit,
+            api: config.api_rate_limit,
+            trust_forwarded_for: config.trust_forwarded_for,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn rate(&self, class: Class) -> Option<Rate> {
+        match class {
+            Class::Html => self.html,
+            Class::Api => self.api,
+        }
+    }
+
+    /// Takes a token for `client`, or returns how many seconds until one is free.
+    fn take(&self, class: Class, client: String) -> Result<(), u64> {
+        let Some(rate) = self.rate(class) else {
+            return Ok(());
+        };
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > PRUNE_AT {
+            buckets.retain(|(class, _), b| {
+                self.rate(*class)
+                    .is_some_and(|r| b.refilled(r, now) < r.burst as f64)
+            });
+        }
+
+        let bucket = buckets.entry((class, client)).or_insert(Bucket {
+            tokens: rate.burst as f64,
+            updated: now,
+        });
+        bucket.tokens = bucket.refilled(rate, now);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait = (1.0 - bucket.tokens) / rate.per_sec();
+            Err((wait.ceil() as u64).max(1))
+        }
+    }
+
+    /// Who is asking: the signed-in user or API key owner, otherwise the client
+    /// address. `X-Forwarded-For` is only believed behind a trusted proxy, and
+    /// then its last entry, the one our proxy added, is used.
+    fn client(&self, req: &Parts) -> String {
+        if let Some(user) = req.extensions.get::<User>() {
+            return format!("user:{}", user.name);
+        }
+        if self.trust_forwarded_for {
+            let forwarded = req
+                .headers
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.rsplit(',').next())
+                .map(str::trim)
+                .filter(|ip| !ip.is_empty());
+            if let Some(ip) = forwarded {
+                return format!("ip:{ip}");
+            }
+        }
+        match req.extensions.get::<ConnectInfo<SocketAddr>>() {
+            Some(ConnectInfo(addr)) => format!("ip:{}", addr.ip()),
+            // Unix sockets have no peer address; everyone shares one bucket.
+            None => "unknown".into(),
+        }
+    }
+
+    fn check(&self, class: Class, req: &Parts) -> Result<Metered, Error> {
+        let metered = Metered {
+            class,
+            client: self.client(req),
+        };
+        self.charge(&metered)?;
+        Ok(metered)
+    }
+
+    /// Takes another token from the bucket a request was charged to.
+    pub fn charge(&self, metered: &Metered) -> Result<(), Error> {
+        let Metered { class, client } = metered;
+        self.take(*class, client.clone()).map_err(|retry_after| {
+            info!(client, class = class.as_str(), "rate limited");
+            METRICS
+                .rate_limited
+                .with_label_values(&[class.as_str()])
+                .inc();
+            Error::TooManyRequests { retry_after }
+        })
+    }
+}
+
+/// The bucket a request took its token from, added to the request for
+/// handlers that make further provider calls on it, like exports fetching
+/// page after page.
+#[derive(Clone)]
+pub struct Metered {
+    class: Class,
+    client: String,
+}
+
+/// Limits searches from the HTML page and feeds. Loading the page without a
+/// query is free, and so is the streaming results page: it only renders the
+/// page around the results, and its `/stream` request is the one counted.
+pub async fn html(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let searching = req.uri().query().is_some_and(|q| {
+        let mut pairs = form_urlencoded::parse(q.as_bytes());
+        pairs.clone().any(|(k, _)| k == "q") && !pairs.any(|(k, v)| k == "stream" && v == "true")
+    });
+    if searching {
+        return actions(State(state), req, next).await;
+    }
+    next.run(req).await
+}
+
+/// Limits form posts that search on the user's behalf, like creating or
+/// running a saved search. Every one counts, against the same bucket as
+/// searches from the page.
+pub async fn actions(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let (mut parts, body) = req.into_parts();
+    match state.limiter.check(Class::Html, &parts) {
+        Ok(metered) => parts.extensions.insert(metered),
+        Err(e) => return e.into_response(),
+    };
+    next.run(Request::from_parts(parts, body)).await
+}
+
+/// Limits calls to the JSON API, answering with a JSON error.
+pub async fn api(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let (mut parts, body) = req.into_parts();
+    match state.limiter.check(Class::Api, &parts) {
+        Ok(metered) => parts.extensions.insert(metered),
+        Err(e) => return JsonError(e).into_response(),
+    };
+    next.run(Request::from_parts(parts, body)).await
+}
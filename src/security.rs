@@ -0,0 +1,74 @@
+use axum::{
+    extract::Request,
+    http::{header, request::Parts, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::tls::Tls;
+
+tokio::task_local! {
+    static NONCE: String;
+}
+
+/// The CSP nonce for the request being rendered. Inline `<script>` and `<style>`
+/// elements must carry it, see `html::script`.
+pub fn nonce() -> String {
+    NONCE.try_with(Clone::clone).unwrap_or_default()
+}
+
+/// Whether the request reached us, or the proxy in front of us, over HTTPS.
+pub fn over_tls(req: &Parts) -> bool {
+    req.extensions.get::<Tls>().is_some()
+        || req
+            .headers
+            .get("x-forwarded-proto")
+            .is_some_and(|proto| proto == "https")
+}
+
+fn content_security_policy(nonce: &str) -> String {
+    format!(
+        "default-src 'self'; \
+         script-src 'self' 'nonce-{nonce}'; \
+         style-src 'self' 'nonce-{nonce}'; \
+         img-src 'self' data:; \
+         object-src 'none'; \
+         base-uri 'none'; \
+         form-action 'self'; \
+         frame-ancestors 'none'"
+    )
+}
+
+/// Adds CSP and the other hardening headers to every response. The nonce is
+/// generated per request and made available to rendering through [`nonce`].
+pub async fn headers(req: Request, next: Next) -> Response {
+    let (parts, body) = req.into_parts();
+    let over_tls = over_tls(&parts);
+    let req = Request::from_parts(parts, body);
+
+    let nonce = uuid::Uuid::new_v4().simple().to_string();
+    let mut resp = NONCE.scope(nonce.clone(), next.run(req)).await;
+
+    let headers = resp.headers_mut();
+    headers.insert(
+        header::CONTENT_SECURITY_POLICY,
+        HeaderValue::from_str(&content_security_policy(&nonce)).unwrap(),
+    );
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    // Searches are in the URL; keep them from leaking to sites opened from results.
+    headers.insert(
+        header::REFERRER_POLICY,
+        HeaderValue::from_static("no-referrer"),
+    );
+    headers.insert(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+    if over_tls {
+        headers.insert(
+            header::STRICT_TRANSPORT_SECURITY,
+            HeaderValue::from_static("max-age=31536000"),
+        );
+    }
+    resp
+}
@@ -0,0 +1,73 @@
+use std::{future::Future, sync::LazyLock, time::Duration};
+
+use tokio::signal;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Cancelled as soon as a shutdown signal arrives; the server stops accepting connections.
+pub static SHUTDOWN: LazyLock<CancellationToken> = LazyLock::new(CancellationToken::new);
+
+/// Cancelled once the drain timeout has passed; outstanding provider requests are aborted.
+pub static ABORT: LazyLock<CancellationToken> = LazyLock::new(CancellationToken::new);
+
+/// How long cancelled handlers get to write their responses after [`ABORT`].
+const ABORT_GRACE: Duration = Duration::from_secs(1);
+
+/// Resolves on SIGINT or SIGTERM.
+async fn signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Watches for a shutdown signal and cancels [`SHUTDOWN`]. Pass to `with_graceful_shutdown`.
+pub async fn on_signal() {
+    signal().await;
+    info!("shutdown signal received, draining connections");
+    SHUTDOWN.cancel();
+}
+
+/// Drives `server` to completion, giving in-flight requests up to `drain_timeout` after
+/// a shutdown signal before aborting outstanding provider calls and returning anyway.
+pub async fn drain<F, E>(server: F, drain_timeout: Duration) -> Result<(), E>
+where
+    F: Future<Output = Result<(), E>>,
+{
+    tokio::pin!(server);
+
+    tokio::select! {
+        res = &mut server => return res,
+        _ = async {
+            SHUTDOWN.cancelled().await;
+            tokio::time::sleep(drain_timeout).await;
+        } => {}
+    }
+
+    warn!(
+        ?drain_timeout,
+        "drain timeout reached, aborting outstanding searches"
+    );
+    ABORT.cancel();
+    match tokio::time::timeout(ABORT_GRACE, server).await {
+        Ok(res) => res,
+        Err(_) => Ok(()),
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{LazyLock, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use axum::{http::StatusCode, Json};
+use maud::{html, Markup};
+use serde::Serialize;
+
+use crate::{
+    error::Error,
+    html::{self, card, column},
+};
+
+/// Providers that can serve searches; all of them are shown on `/status`
+/// even before their first call.
+pub const PROVIDERS: &[&str] = &["github"];
+
+/// How many recent latencies are kept per provider for the percentiles.
+const LATENCY_WINDOW: usize = 200;
+
+/// A provider counts as failing once this many calls failed within
+/// [`FAILURE_WINDOW`] without a success in between.
+const FAILURE_THRESHOLD: usize = 3;
+const FAILURE_WINDOW: Duration = Duration::from_secs(60);
+
+pub static PROVIDER_STATUS: LazyLock<Mutex<BTreeMap<&'static str, ProviderStatus>>> =
+    LazyLock::new(|| {
+        Mutex::new(
+            PROVIDERS
+                .iter()
+                .map(|p| (*p, ProviderStatus::default()))
+                .collect(),
+        )
+    });
+
+#[derive(Default, Debug, Clone)]
+pub struct ProviderStatus {
+    pub last_success: Option<SystemTime>,
+    pub last_error: Option<(SystemTime, String)>,
+    /// When recent calls failed in a way that is neither the query's nor a
+    /// user's fault; cleared by the next success.
+    recent_failures: VecDeque<SystemTime>,
+    pub rate_limit_remaining: Option<i64>,
+    pub rate_limit_reset: Option<SystemTime>,
+    latencies: VecDeque<Duration>,
+}
+
+impl ProviderStatus {
+    pub fn healthy(&self) -> bool {
+        let now = SystemTime::now();
+        let recent = self
+            .recent_failures
+            .iter()
+            .filter(|at| now.duration_since(**at).unwrap_or_default() < FAILURE_WINDOW)
+            .count();
+        recent < FAILURE_THRESHOLD
+    }
+
+    /// Latency at percentile `p` (0.0..=1.0) over the recent window.
+    pub fn latency_percentile(&self, p: f64) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<_> = self.latencies.iter().copied().collect();
+        sorted.sort();
+        let idx = ((sorted.len() - 1) as f64 * p).round() as usize;
+        Some(sorted[idx])
+    }
+}
+
+/// Records the outcome of one provider call. `shared` says whether the call
+/// used the instance's own credentials; failures of calls made with a user's
+/// token say nothing about the instance and do not count against readiness.
+pub fn record<T>(provider: &'static str, latency: Duration, res: &Result<T, Error>, shared: bool) {
+    let mut all = PROVIDER_STATUS.lock().unwrap();
+    let status = all.entry(provider).or_default();
+
+    if status.latencies.len() == LATENCY_WINDOW {
+        status.latencies.pop_front();
+    }
+    status.latencies.push_back(latency);
+
+    let now = SystemTime::now();
+    match res {
+        Ok(_) => {
+            status.last_success = Some(now);
+            status.recent_failures.clear();
+        }
+        Err(e) => {
+            status.last_error = Some((now, e.to_string()));
+            let counts = shared
+                && !matches!(
+                    e,
+                    Error::BadQuery(_) | Error::Cancelled | Error::Auth | Error::RateLimited { .. }
+                );
+            if counts {
+                if status.recent_failures.len() == FAILURE_THRESHOLD {
+                    status.recent_failures.pop_front();
+                }
+                status.recent_failures.push_back(now);
+            }
+        }
+    }
+}
+
+pub fn record_rate_limit(provider: &'static str, remaining: i64, reset: Option<SystemTime>) {
+    let mut all = PROVIDER_STATUS.lock().unwrap();
+    let status = all.entry(provider).or_default();
+    status.rate_limit_remaining = Some(remaining);
+    status.rate_limit_reset = reset;
+}
+
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+#[derive(Serialize)]
+pub struct Readiness {
+    ready: bool,
+    providers: BTreeMap<&'static str, bool>,
+}
+
+/// Ready once at least one provider is healthy, that is has not failed
+/// repeatedly in the last minute. Configuration is loaded before
+/// the listener is bound, so by the time this can answer it is in place.
+pub async fn readyz() -> (StatusCode, Json<Readiness>) {
+    let providers: BTreeMap<_, _> = PROVIDER_STATUS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, s)| (*name, s.healthy()))
+        .collect();
+    let ready = providers.values().any(|healthy| *healthy);
+    let code = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (code, Json(Readiness { ready, providers }))
+}
+
+pub async fn status_page() -> Markup {
+    let providers = PROVIDER_STATUS.lock().unwrap().clone();
+
+    column(
+        "Status",
+        html! {
+            h1 class="text-3xl font-bold text-white" { "Provider Status" }
+            @for (name, s) in &providers {
+                (card(html! {
+                    h2 class="text-xl font-semibold text-white mb-4" {
+                        (name) " "
+                        @if s.healthy() {
+                            span class="text-green-400 text-sm" { "healthy" }
+                        } @else {
+                            span class="text-red-400 text-sm" { "failing" }
+                        }
+                    }
+                    dl class="grid grid-cols-2 gap-2 text-sm text-slate-300" {
+                        dt { "Last success" }
+                        dd { (ago(s.last_success)) }
+                        dt { "Last error" }
+                        dd {
+                            @match &s.last_error {
+                                Some((at, msg)) => { (ago(Some(*at))) ": " (msg) }
+                                None => "never",
+                            }
+                        }
+                        dt { "Latency p50 / p90 / p99" }
+                        dd {
+                            (millis(s.latency_percentile(0.5))) " / "
+                            (millis(s.latency_percentile(0.9))) " / "
+                            (millis(s.latency_percentile(0.99)))
+                        }
+                        dt { "Rate limit remaining" }
+                        dd {
+                            @match s.rate_limit_remaining {
+                                Some(n) => { (n) " (resets " (until(s.rate_limit_reset)) ")" }
+                                None => "unknown",
+                            }
+                        }
+                    }
+                }))
+            }
+        },
+    )
+}
+
+fn ago(t: Option<SystemTime>) -> String {
+    match t {
+        Some(t) => html::ago(t),
+        None => "never".into(),
+    }
+}
+
+fn until(t: Option<SystemTime>) -> String {
+    match t.and_then(|t| t.duration_since(SystemTime::now()).ok()) {
+        Some(d) => format!("in {}s", d.as_secs()),
+        None => "now".into(),
+    }
+}
+
+fn millis(d: Option<Duration>) -> String {
+    match d {
+        Some(d) => format!("{}ms", d.as_millis()),
+        None => "-".into(),
+    }
+}
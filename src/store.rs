@@ -0,0 +1,154 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use axum::response::{IntoResponse, Redirect, Response};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::{error, warn};
+
+/// How long [`Store::update_later`] waits, collecting further changes into the
+/// same write.
+const WRITE_DELAY: Duration = Duration::from_secs(1);
+
+/// A value kept in memory and mirrored to a JSON file in the data directory.
+/// Without a data directory it lives in memory only and is lost on restart.
+pub struct Store<T> {
+    path: Option<PathBuf>,
+    value: Mutex<T>,
+    /// Held while writing, so writes land in the order they were made. Taken
+    /// before `value`.
+    writing: Mutex<()>,
+    /// A write from [`Store::update_later`] is scheduled.
+    pending: AtomicBool,
+}
+
+impl<T: Default + Serialize + DeserializeOwned> Store<T> {
+    /// Loads `name` from `data_dir`. A file that exists but can't be read is left
+    /// alone and the store runs in memory, rather than overwriting it on the next
+    /// update.
+    pub fn open(data_dir: Option<&Path>, name: &str) -> Self {
+        let Some(dir) = data_dir else {
+            return Store::in_memory();
+        };
+        let path = dir.join(name);
+        match load(&path) {
+            Ok(value) => Store {
+                path: Some(path),
+                value: Mutex::new(value),
+                writing: Mutex::new(()),
+                pending: AtomicBool::new(false),
+            },
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "keeping store in memory only");
+                Store::in_memory()
+            }
+        }
+    }
+
+    fn in_memory() -> Self {
+        Store {
+            path: None,
+            value: Mutex::new(T::default()),
+            writing: Mutex::new(()),
+            pending: AtomicBool::new(false),
+        }
+    }
+
+    pub fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.value.lock().unwrap())
+    }
+
+    /// Applies `f` and writes the new value out before returning. If writing
+    /// fails the change is still kept in memory.
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> io::Result<R> {
+        let _writing = self.writing.lock().unwrap();
+        let mut value = self.value.lock().unwrap();
+        let res = f(&mut value);
+        if let Some(path) = &self.path {
+            let json = serde_json::to_vec_pretty(&*value)?;
+            drop(value);
+            write(path, &json)?;
+        }
+        Ok(res)
+    }
+
+    /// [`Store::update`] for form handlers: a failed write is logged, and the
+    /// browser is sent on to `to` either way.
+    pub fn update_and_redirect(&self, to: &str, f: impl FnOnce(&mut T)) -> Response {
+        if let Err(e) = self.update(f) {
+            error!(path = ?self.path, error = %e, "could not save store");
+        }
+        Redirect::to(to).into_response()
+    }
+
+    /// Applies `f` in memory and writes the value out a moment later on the
+    /// blocking pool, together with whatever else changed by then. For stores
+    /// updated on every request; call [`Store::flush`] before exiting.
+    pub fn update_later<R>(self: &Arc<Self>, f: impl FnOnce(&mut T) -> R) -> R
+    where
+        T: Send + 'static,
+    {
+        let res = f(&mut self.value.lock().unwrap());
+        if self.path.is_some() && !self.pending.swap(true, Ordering::AcqRel) {
+            let store = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(WRITE_DELAY).await;
+                let _ = tokio::task::spawn_blocking(move || store.flush()).await;
+            });
+        }
+        res
+    }
+
+    /// Writes out changes from [`Store::update_later`] that are still waiting.
+    pub fn flush(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let _writing = self.writing.lock().unwrap();
+        if !self.pending.swap(false, Ordering::AcqRel) {
+            return;
+        }
+        let json = serde_json::to_vec_pretty(&*self.value.lock().unwrap());
+        if let Err(e) = json.map_err(io::Error::from).and_then(|j| write(path, &j)) {
+            error!(path = %path.display(), error = %e, "could not save store");
+        }
+    }
+}
+
+/// Now, in seconds since the Unix epoch, which is how stores keep time.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// A timestamp as kept by [`unix_now`].
+pub fn unix_time(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+/// Replaces the file through a temporary one, so a crash never leaves it half
+/// written.
+fn write(path: &Path, json: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, path)
+}
+
+fn load<T: Default + DeserializeOwned>(path: &Path) -> io::Result<T> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    match fs::read(path) {
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,111 @@
+use std::{convert::Infallible, future};
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+    Extension,
+};
+use futures_util::{stream, Stream, StreamExt};
+use maud::{html, Markup};
+use serde::Deserialize;
+use url::form_urlencoded::byte_serialize;
+
+use crate::{
+    auth::User,
+    error::Error,
+    history,
+    html::{error_card, pager, result_list, script},
+    profile::Profile,
+    run_search, AppState,
+};
+
+/// Fills `#stream` from the event stream as each provider answers. If the
+/// stream can't be opened at all, e.g. when rate limited, the page is loaded
+/// again the usual way, which explains what went wrong.
+const CLIENT: &str = "
+(function () {
+    const results = document.getElementById('stream');
+    const source = new EventSource(results.dataset.stream);
+    let waiting = true;
+    function insert(e) {
+        if (waiting) {
+            results.replaceChildren();
+            waiting = false;
+        }
+        results.insertAdjacentHTML('beforeend', e.data);
+    }
+    source.addEventListener('results', insert);
+    source.addEventListener('failed', insert);
+    source.addEventListener('done', function () {
+        source.close();
+    });
+    source.onerror = function () {
+        source.close();
+        location.replace(results.dataset.fallback);
+    };
+})();
+";
+
+#[derive(Deserialize)]
+pub struct StreamQuery {
+    q: Option<String>,
+}
+
+/// Where the streaming results page puts results as they arrive.
+pub fn placeholder(query: &str) -> Markup {
+    let q = byte_serialize(query.as_bytes()).collect::<String>();
+    html! {
+        div id="stream" class="space-y-6" data-stream={ "/stream?q=" (q) } data-fallback={ "/?q=" (q) } {
+            p class="text-center text-slate-400 py-8" { "Searching…" }
+        }
+        (script(CLIENT))
+    }
+}
+
+/// `/stream?q=...`: server-sent events with rendered fragments, a `results` or
+/// `failed` event per provider as soon as it answers, then `done`.
+pub async fn results(
+    State(state): State<AppState>,
+    profile: Profile,
+    user: Option<Extension<User>>,
+    Query(search): Query<StreamQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let query = search.q.unwrap_or_default();
+    let user = user.map(|Extension(u)| u);
+
+    // GitHub is the only provider so far, so there is a single event before `done`.
+    let providers = stream::once(async move {
+        let provider = profile.route(&query).0.unwrap_or_default();
+        let event = match run_search(&state, &profile, &query).await {
+            Ok(res) => {
+                history::record(&state, user.as_ref(), &profile, &query, res.total_count);
+                let fragment = html! {
+                    section class="space-y-4" data-provider=(provider) {
+                        p class="text-sm text-slate-400" {
+                            (res.total_count) " results from " (provider)
+                        }
+                        (result_list(&query, &res, state.config.clean_links, profile.new_tab))
+                        (pager(&query, 1, res.total_count, profile.per_page))
+                    }
+                };
+                Event::default()
+                    .event("results")
+                    .data(fragment.into_string())
+            }
+            Err(e) => Event::default()
+                .event("failed")
+                .data(failed(provider, &e).into_string()),
+        };
+        Ok(event)
+    });
+    let done = stream::once(future::ready(Ok(Event::default().event("done").data(""))));
+    Sse::new(providers.chain(done)).keep_alive(KeepAlive::default())
+}
+
+fn failed(provider: &str, e: &Error) -> Markup {
+    html! {
+        section data-provider=(provider) {
+            (error_card(e.title(), &e.to_string(), &e.hint()))
+        }
+    }
+}
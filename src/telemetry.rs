@@ -0,0 +1,87 @@
+use axum::http::Request;
+use axum_extra::extract::cookie::Key;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tower_http::{
+    classify::{ServerErrorsAsFailures, SharedClassifier},
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, RequestId, SetRequestIdLayer},
+    trace::{DefaultOnResponse, MakeSpan, TraceLayer},
+};
+use tracing::{info_span, Level, Span};
+use tracing_subscriber::EnvFilter;
+
+use crate::config::LogFormat;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Installs the global subscriber. Filtering follows `RUST_LOG`, defaulting to `info`.
+pub fn init(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+
+    match format {
+        LogFormat::Pretty => builder.init(),
+        LogFormat::Json => builder.json().flatten_event(true).init(),
+    }
+}
+
+/// Identifies queries in logs so searches can be correlated without writing
+/// what people searched for. Keyed, so that someone with the logs can't test
+/// guesses without the key too. Hashes stay the same for as long as
+/// `MY_SEARCH_SECRET` does; without one, only until the next restart.
+#[derive(Clone)]
+pub struct QueryHasher(Hmac<Sha256>);
+
+impl QueryHasher {
+    /// Derives its own key from `key`, so hashes in the logs have nothing to
+    /// do with cookie signatures.
+    pub fn new(key: &Key) -> Self {
+        let mut derive = Hmac::<Sha256>::new_from_slice(key.master()).expect("any key length");
+        derive.update(b"my-search query hash");
+        let key = derive.finalize().into_bytes();
+        QueryHasher(Hmac::new_from_slice(&key).expect("any key length"))
+    }
+
+    pub fn hash(&self, q: &str) -> String {
+        let mut mac = self.0.clone();
+        mac.update(q.as_bytes());
+        hex::encode(&mac.finalize().into_bytes()[..8])
+    }
+}
+
+pub fn set_request_id() -> SetRequestIdLayer<MakeRequestUuid> {
+    SetRequestIdLayer::new(REQUEST_ID_HEADER.parse().unwrap(), MakeRequestUuid)
+}
+
+pub fn propagate_request_id() -> PropagateRequestIdLayer {
+    PropagateRequestIdLayer::new(REQUEST_ID_HEADER.parse().unwrap())
+}
+
+pub fn trace(
+) -> TraceLayer<SharedClassifier<ServerErrorsAsFailures>, RequestSpan, (), DefaultOnResponse> {
+    TraceLayer::new_for_http()
+        .make_span_with(RequestSpan)
+        .on_request(())
+        .on_response(DefaultOnResponse::new().level(Level::INFO))
+}
+
+/// Span for one HTTP request, tagged with the id set by [`set_request_id`].
+#[derive(Clone, Copy)]
+pub struct RequestSpan;
+
+impl<B> MakeSpan<B> for RequestSpan {
+    fn make_span(&mut self, req: &Request<B>) -> Span {
+        let request_id = req
+            .extensions()
+            .get::<RequestId>()
+            .and_then(|id| id.header_value().to_str().ok())
+            .unwrap_or_default();
+
+        info_span!(
+            "request",
+            request_id,
+            method = %req.method(),
+            path = req.uri().path(),
+        )
+    }
+}
@@ -0,0 +1,108 @@
+use std::{
+    io,
+    net::{SocketAddr, TcpListener},
+    path::Path,
+    time::SystemTime,
+};
+
+use axum::{
+    extract::Request,
+    http::{header, uri::Authority, StatusCode, Uri},
+    response::{IntoResponse, Redirect, Response},
+    Extension, Router,
+};
+use axum_server::{tls_rustls::RustlsConfig, Handle};
+use tracing::{info, warn};
+
+use crate::{config::TlsConfig, shutdown};
+
+/// Marker extension on requests that arrived over our own TLS listener.
+#[derive(Clone, Copy)]
+pub struct Tls;
+
+/// Serves `app` over HTTPS until a shutdown signal, reloading the certificate
+/// whenever the files on disk change.
+pub async fn serve(app: Router, listener: TcpListener, tls: &TlsConfig) -> io::Result<()> {
+    // Both ring and aws-lc could be linked in through dependencies; pick one explicitly.
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let rustls = RustlsConfig::from_pem_file(&tls.cert, &tls.key).await?;
+    tokio::spawn(watch(rustls.clone(), tls.clone()));
+
+    let handle = Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            shutdown::on_signal().await;
+            handle.graceful_shutdown(None);
+        }
+    });
+
+    axum_server::from_tcp_rustls(listener, rustls)
+        .handle(handle)
+        .serve(
+            app.layer(Extension(Tls))
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata().and_then(|m| m.modified()).ok()
+}
+
+async fn watch(rustls: RustlsConfig, tls: TlsConfig) {
+    let mut seen = (modified(&tls.cert), modified(&tls.key));
+    let mut interval = tokio::time::interval(tls.reload_interval);
+
+    loop {
+        interval.tick().await;
+        let current = (modified(&tls.cert), modified(&tls.key));
+        if current == seen {
+            continue;
+        }
+        // Keep serving the old certificate if the new files are half-written or broken;
+        // the next change will be picked up again.
+        match rustls.reload_from_pem_file(&tls.cert, &tls.key).await {
+            Ok(()) => info!(cert = %tls.cert.display(), "reloaded TLS certificate"),
+            Err(e) => warn!(error = %e, "failed to reload TLS certificate"),
+        }
+        seen = current;
+    }
+}
+
+/// Plain HTTP app that permanently redirects every request to `https_port`.
+pub fn redirect_app(https_port: u16) -> Router {
+    Router::new().fallback(move |req: Request| async move { redirect(req, https_port) })
+}
+
+fn redirect(req: Request, https_port: u16) -> Response {
+    let host = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.parse::<Authority>().ok());
+    let Some(host) = host else {
+        return (StatusCode::BAD_REQUEST, "Missing Host header").into_response();
+    };
+
+    let authority = match https_port {
+        443 => host.host().to_owned(),
+        port => format!("{}:{port}", host.host()),
+    };
+    let path = req
+        .uri()
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or("/");
+
+    match Uri::builder()
+        .scheme("https")
+        .authority(authority)
+        .path_and_query(path)
+        .build()
+    {
+        Ok(uri) => Redirect::permanent(&uri.to_string()).into_response(),
+        Err(_) => (StatusCode::BAD_REQUEST, "Bad Host header").into_response(),
+    }
+}
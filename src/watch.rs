@@ -0,0 +1,597 @@
+use std::{collections::BTreeSet, time::Duration};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    Extension, Form,
+};
+use maud::{html, Markup};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::{
+    auth::User,
+    html::{ago, card, centered, column, error_card},
+    links,
+    profile::Profile,
+    run_search, shutdown,
+    store::{unix_now, unix_time},
+    webhook::{self, Delivery, Webhook},
+    AppState,
+};
+
+/// How often the scheduler looks for saved searches that are due.
+const TICK: Duration = Duration::from_secs(60);
+
+/// Intervals offered on the dashboard, in minutes. Each run is a provider call
+/// on the owner's token, so nothing shorter than a quarter hour.
+const INTERVALS: &[u64] = &[15, 60, 6 * 60, 24 * 60];
+
+const MAX_PER_USER: usize = 50;
+
+/// New items kept per saved search until they are marked as seen.
+const MAX_NEW_ITEMS: usize = 100;
+
+/// Result IDs remembered per saved search.
+const MAX_SEEN: usize = 1000;
+
+/// Webhook deliveries kept per saved search, and how many the dashboard shows.
+const MAX_DELIVERIES: usize = 20;
+const SHOWN_DELIVERIES: usize = 5;
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Watches {
+    next_id: u64,
+    saved: Vec<Saved>,
+}
+
+impl Watches {
+    fn get_mut(&mut self, id: u64, user: &Option<String>) -> Option<&mut Saved> {
+        self.saved
+            .iter_mut()
+            .find(|s| s.id == id && &s.user == user)
+    }
+}
+
+/// A pinned search, re-run every `interval_mins` by [`scheduler`].
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Saved {
+    pub id: u64,
+    pub user: Option<String>,
+    pub query: String,
+    pub interval_mins: u64,
+    /// Seconds since the Unix epoch.
+    pub last_run: Option<u64>,
+    pub last_error: Option<String>,
+    /// Result IDs of every run so far, up to [`MAX_SEEN`]; anything not in
+    /// here is new. Results that drop out and come back aren't new again.
+    pub seen: BTreeSet<i64>,
+    pub new_items: Vec<NewItem>,
+    #[serde(default)]
+    pub webhook: Option<Webhook>,
+    /// Oldest first.
+    #[serde(default)]
+    pub deliveries: Vec<Delivery>,
+}
+
+impl Saved {
+    fn due(&self, now: u64) -> bool {
+        self.last_run
+            .is_none_or(|last| last + self.interval_mins * 60 <= now)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NewItem {
+    pub id: i64,
+    pub title: String,
+    pub html_url: String,
+    /// Seconds since the Unix epoch.
+    pub found_at: u64,
+}
+
+/// Re-runs saved searches as they come due, until shutdown.
+pub async fn scheduler(state: AppState) {
+    let mut interval = tokio::time::interval(TICK);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown::SHUTDOWN.cancelled() => return,
+        }
+        let now = unix_now();
+        let due: Vec<u64> = state.watches.read(|w| {
+            w.saved
+                .iter()
+                .filter(|s| s.due(now))
+                .map(|s| s.id)
+                .collect()
+        });
+        for id in due {
+            check(&state, id).await;
+        }
+    }
+}
+
+/// Runs one saved search with its owner's profile and records what is new
+/// since the previous run, posting it to the webhook if there is one. The
+/// first run only establishes the baseline.
+async fn check(state: &AppState, id: u64) {
+    let Some((user, query)) = state.watches.read(|w| {
+        w.saved
+            .iter()
+            .find(|s| s.id == id)
+            .map(|s| (s.user.clone(), s.query.clone()))
+    }) else {
+        return;
+    };
+    let profile: Profile = user
+        .as_ref()
+        .and_then(|u| state.profiles.read(|all| all.get(u).cloned()))
+        .unwrap_or_default();
+
+    let res = run_search(state, &profile, &query).await;
+    let now = unix_now();
+
+    // Written out in the background: the scheduler may check many searches
+    // in a row and shouldn't block a worker on each write.
+    let deliver = state.watches.update_later(|w| {
+        let s = w.get_mut(id, &user)?;
+        let first_run = s.last_run.is_none();
+        s.last_run = Some(now);
+        match res {
+            Ok(root) => {
+                s.last_error = None;
+                let found: Vec<NewItem> = root
+                    .items
+                    .iter()
+                    .filter(|item| !first_run && !s.seen.contains(&item.id))
+                    .map(|item| NewItem {
+                        id: item.id,
+                        title: item.title.clone(),
+                        html_url: item.html_url.clone(),
+                        found_at: now,
+                    })
+                    .collect();
+                s.seen.extend(root.items.iter().map(|item| item.id));
+                // IDs grow over time, so the oldest results are forgotten first.
+                while s.seen.len() > MAX_SEEN {
+                    s.seen.pop_first();
+                }
+                if found.is_empty() {
+                    return None;
+                }
+                info!(id, new = found.len(), "saved search has new results");
+                s.new_items.extend(found.iter().cloned());
+                let excess = s.new_items.len().saturating_sub(MAX_NEW_ITEMS);
+                s.new_items.drain(..excess);
+                s.webhook.clone().map(|hook| (hook, found))
+            }
+            Err(e) => {
+                warn!(id, error = %e, "saved search failed");
+                s.last_error = Some(e.to_string());
+                None
+            }
+        }
+    });
+    if let Some((hook, found)) = deliver {
+        spawn_delivery(state, hook, id, user, query, found, now);
+    }
+}
+
+/// Delivers in the background and logs the outcome once it is known. Retries
+/// can take over a minute, which neither a form post nor the scheduler should
+/// wait for.
+fn spawn_delivery(
+    state: &AppState,
+    hook: Webhook,
+    id: u64,
+    user: Option<String>,
+    query: String,
+    items: Vec<NewItem>,
+    at: u64,
+) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        let delivery = webhook::deliver(&state, &hook, id, &query, &items, at).await;
+        log_delivery(&state, id, &user, delivery);
+    });
+}
+
+/// Marks a test delivery for saved search `id` as under way until dropped.
+struct TestDelivery {
+    state: AppState,
+    id: u64,
+}
+
+impl TestDelivery {
+    fn start(state: &AppState, id: u64) -> Option<Self> {
+        state
+            .test_deliveries
+            .lock()
+            .unwrap()
+            .insert(id)
+            .then(|| TestDelivery {
+                state: state.clone(),
+                id,
+            })
+    }
+}
+
+impl Drop for TestDelivery {
+    fn drop(&mut self) {
+        self.state.test_deliveries.lock().unwrap().remove(&self.id);
+    }
+}
+
+fn log_delivery(state: &AppState, id: u64, user: &Option<String>, delivery: Delivery) {
+    state.watches.update_later(|w| {
+        if let Some(s) = w.get_mut(id, user) {
+            s.deliveries.push(delivery);
+            let excess = s.deliveries.len().saturating_sub(MAX_DELIVERIES);
+            s.deliveries.drain(..excess);
+        }
+    });
+}
+
+#[derive(Deserialize)]
+pub struct NewSaved {
+    query: String,
+    interval_mins: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct WebhookForm {
+    url: String,
+    format: Option<String>,
+    secret: Option<String>,
+}
+
+pub async fn dashboard(State(state): State<AppState>, user: Option<Extension<User>>) -> Markup {
+    let owner = User::owner(&user);
+    let saved: Vec<Saved> = state.watches.read(|w| {
+        w.saved
+            .iter()
+            .filter(|s| s.user == owner)
+            .cloned()
+            .collect()
+    });
+
+    column(
+        "Saved Searches",
+        html! {
+            h1 class="text-3xl font-bold text-white" { "Saved Searches" }
+            (card(html! {
+                form class="flex gap-4" method="post" action="/saved" {
+                    input
+                        type="text"
+                        name="query"
+                        placeholder="repo:our-org/app label:bug is:open"
+                        class="flex-1 px-4 py-2 bg-slate-800/50 text-white placeholder-slate-400 border border-slate-700/50 rounded-xl focus:outline-none"
+                        required;
+                    (interval_select(60))
+                    button type="submit" class="lift-button px-4 py-2 bg-blue-800 hover:bg-blue-700 text-white rounded-xl" { "Watch" }
+                }
+            }))
+            @if saved.is_empty() {
+                (card(html! {
+                    p class="text-center text-slate-400 py-8" { "Nothing is being watched yet." }
+                }))
+            }
+            @for s in &saved {
+                (saved_card(s, state.config.clean_links))
+            }
+        },
+    )
+}
+
+fn interval_select(selected: u64) -> Markup {
+    html! {
+        select name="interval_mins" class="bg-slate-800/50 text-white rounded-xl px-4 py-2" {
+            @for mins in INTERVALS {
+                option value=(mins) selected[*mins == selected] {
+                    @if mins % 60 == 0 { "every " (mins / 60) "h" } @else { "every " (mins) "m" }
+                }
+            }
+        }
+    }
+}
+
+fn saved_card(s: &Saved, clean_links: bool) -> Markup {
+    card(html! {
+        div class="flex items-center gap-4 mb-2" {
+            h2 class="flex-1 text-xl font-semibold text-white" {
+                a href={ "/?q=" (url::form_urlencoded::byte_serialize(s.query.as_bytes()).collect::<String>()) } { (s.query) }
+            }
+            @if !s.new_items.is_empty() {
+                span class="text-green-400 text-sm" { (s.new_items.len()) " new" }
+            }
+        }
+        p class="text-sm text-slate-400 mb-4" {
+            "Every " (s.interval_mins) " minutes · last run "
+            @match s.last_run {
+                Some(t) => (ago(unix_time(t))),
+                None => "pending",
+            }
+            @if let Some(e) = &s.last_error {
+                " · " span class="text-red-400" { (e) }
+            }
+        }
+        @if !s.new_items.is_empty() {
+            div class="space-y-2 mb-4" {
+                @for item in s.new_items.iter().rev() {
+                    div class="result-item rounded-xl p-3" {
+                        @match links::outbound(&item.html_url, clean_links) {
+                            Some(url) => {
+                                a href=(url) target="_blank" rel="noopener noreferrer" class="text-white" { (item.title) }
+                            }
+                            None => span class="text-white" { (item.title) },
+                        }
+                        span class="text-xs text-slate-500" { " found " (ago(unix_time(item.found_at))) }
+                    }
+                }
+            }
+        }
+        (webhook_section(s))
+        div class="flex gap-4 text-sm" {
+            form method="post" action={ "/saved/" (s.id) "/run" } {
+                button type="submit" class="text-blue-400 hover:text-blue-300" { "Run now" }
+            }
+            @if !s.new_items.is_empty() {
+                form method="post" action={ "/saved/" (s.id) "/seen" } {
+                    button type="submit" class="text-slate-400 hover:text-slate-300" { "Mark as seen" }
+                }
+            }
+            form method="post" action={ "/saved/" (s.id) "/delete" } {
+                button type="submit" class="text-slate-400 hover:text-red-400" { "Delete" }
+            }
+        }
+    })
+}
+
+fn webhook_section(s: &Saved) -> Markup {
+    let format = s
+        .webhook
+        .as_ref()
+        .map_or(webhook::Format::Generic, |h| h.format);
+    html! {
+        details class="mb-4 text-sm" open[s.webhook.is_some()] {
+            summary class="cursor-pointer text-slate-300" {
+                "Webhook"
+                @if let Some(hook) = &s.webhook {
+                    span class="text-slate-500" { " · " (hook.format.as_str()) " · " (hook.url) }
+                }
+            }
+            form class="flex flex-wrap gap-2 mt-2" method="post" action={ "/saved/" (s.id) "/webhook" } {
+                input
+                    type="url"
+                    name="url"
+                    value=[s.webhook.as_ref().map(|h| &h.url)]
+                    placeholder="https://hooks.example.com/... (empty to remove)"
+                    class="flex-1 px-3 py-1 bg-slate-800/50 text-white placeholder-slate-400 border border-slate-700/50 rounded-xl focus:outline-none";
+                select name="format" class="bg-slate-800/50 text-white rounded-xl px-3 py-1" {
+                    @for f in webhook::Format::ALL {
+                        option value=(f.as_str()) selected[f == format] { (f.as_str()) }
+                    }
+                }
+                input
+                    type="password"
+                    name="secret"
+                    autocomplete="off"
+                    placeholder=(if s.webhook.as_ref().is_some_and(|h| h.secret.is_some()) { "Secret (unchanged)" } else { "Signing secret" })
+                    class="px-3 py-1 bg-slate-800/50 text-white placeholder-slate-400 border border-slate-700/50 rounded-xl focus:outline-none";
+                button type="submit" class="text-blue-400 hover:text-blue-300" { "Save" }
+            }
+            @if s.webhook.is_some() {
+                form class="mt-2" method="post" action={ "/saved/" (s.id) "/webhook/test" } {
+                    button type="submit" class="text-blue-400 hover:text-blue-300" { "Send test delivery" }
+                }
+            }
+            @if !s.deliveries.is_empty() {
+                ul class="mt-2 space-y-1" {
+                    @for d in s.deliveries.iter().rev().take(SHOWN_DELIVERIES) {
+                        li class="text-slate-400" {
+                            @if d.ok() {
+                                span class="text-green-400" { "Delivered" }
+                            } @else {
+                                span class="text-red-400" { "Failed" }
+                            }
+                            " " (ago(unix_time(d.at))) " · " (d.items) " items · "
+                            (d.attempts) @if d.attempts == 1 { " attempt" } @else { " attempts" }
+                            @if let Some(status) = d.status { " · HTTP " (status) }
+                            @if let Some(e) = &d.error { " · " (e) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Button on the results page that pins the current query.
+pub fn watch_button(query: &str) -> Markup {
+    html! {
+        form method="post" action="/saved" {
+            input type="hidden" name="query" value=(query);
+            button type="submit" class="text-sm text-blue-400 hover:text-blue-300" { "Watch this search" }
+        }
+    }
+}
+
+pub async fn create(
+    State(state): State<AppState>,
+    user: Option<Extension<User>>,
+    Form(form): Form<NewSaved>,
+) -> Response {
+    let owner = User::owner(&user);
+    let query = form.query.trim().to_owned();
+    let interval_mins = form
+        .interval_mins
+        .filter(|m| INTERVALS.contains(m))
+        .unwrap_or(60);
+    if query.is_empty() {
+        return Redirect::to("/saved").into_response();
+    }
+
+    let created = state.watches.update(|w| {
+        if w.saved.iter().filter(|s| s.user == owner).count() >= MAX_PER_USER {
+            return None;
+        }
+        let id = w.next_id;
+        w.next_id += 1;
+        w.saved.push(Saved {
+            id,
+            user: owner.clone(),
+            query,
+            interval_mins,
+            last_run: None,
+            last_error: None,
+            seen: BTreeSet::new(),
+            new_items: Vec::new(),
+            webhook: None,
+            deliveries: Vec::new(),
+        });
+        Some(id)
+    });
+    match created {
+        Ok(Some(id)) => {
+            // Establish the baseline right away so the first scheduled run can report news.
+            check(&state, id).await;
+            Redirect::to("/saved").into_response()
+        }
+        Ok(None) => (
+            StatusCode::BAD_REQUEST,
+            centered(
+                "Too Many Saved Searches",
+                "max-w-md",
+                error_card(
+                    "Too Many Saved Searches",
+                    &format!("You can watch up to {MAX_PER_USER} searches."),
+                    "Delete one you no longer need first.",
+                ),
+            ),
+        )
+            .into_response(),
+        Err(e) => {
+            error!(error = %e, "could not save saved searches");
+            Redirect::to("/saved").into_response()
+        }
+    }
+}
+
+pub async fn run_now(
+    State(state): State<AppState>,
+    user: Option<Extension<User>>,
+    Path(id): Path<u64>,
+) -> Response {
+    let owner = User::owner(&user);
+    let mine = state
+        .watches
+        .read(|w| w.saved.iter().any(|s| s.id == id && s.user == owner));
+    if mine {
+        check(&state, id).await;
+    }
+    Redirect::to("/saved").into_response()
+}
+
+pub async fn mark_seen(
+    State(state): State<AppState>,
+    user: Option<Extension<User>>,
+    Path(id): Path<u64>,
+) -> Response {
+    let owner = User::owner(&user);
+    state.watches.update_and_redirect("/saved", |w| {
+        if let Some(s) = w.get_mut(id, &owner) {
+            s.new_items.clear();
+        }
+    })
+}
+
+pub async fn delete(
+    State(state): State<AppState>,
+    user: Option<Extension<User>>,
+    Path(id): Path<u64>,
+) -> Response {
+    let owner = User::owner(&user);
+    state.watches.update_and_redirect("/saved", |w| {
+        w.saved.retain(|s| !(s.id == id && s.user == owner))
+    })
+}
+
+/// Sets the webhook of a saved search, or removes it when `url` is empty. An
+/// empty secret keeps the current one.
+pub async fn set_webhook(
+    State(state): State<AppState>,
+    user: Option<Extension<User>>,
+    Path(id): Path<u64>,
+    Form(form): Form<WebhookForm>,
+) -> Response {
+    let owner = User::owner(&user);
+    let url = form.url.trim().to_owned();
+    if url.is_empty() {
+        return state.watches.update_and_redirect("/saved", |w| {
+            if let Some(s) = w.get_mut(id, &owner) {
+                s.webhook = None;
+            }
+        });
+    }
+    let format = form.format.as_deref().and_then(webhook::Format::parse);
+    let (Some(format), true) = (
+        format,
+        webhook::valid_url(&url, state.config.webhook_allow_private),
+    ) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            centered(
+                "Invalid Webhook",
+                "max-w-md",
+                error_card(
+                    "Invalid Webhook",
+                    "Webhooks need a public http:// or https:// URL and one of the listed formats.",
+                    "Go back and check the address.",
+                ),
+            ),
+        )
+            .into_response();
+    };
+    let secret = form.secret.filter(|s| !s.is_empty());
+    state.watches.update_and_redirect("/saved", |w| {
+        if let Some(s) = w.get_mut(id, &owner) {
+            let secret = secret.or_else(|| s.webhook.take().and_then(|h| h.secret));
+            s.webhook = Some(Webhook {
+                url,
+                format,
+                secret,
+            });
+        }
+    })
+}
+
+/// Posts the unseen items, possibly none, so a receiver can be tried out. The
+/// outcome shows up in the delivery log. While one test is still being
+/// delivered, with its retries, further tests of the same search are ignored.
+pub async fn test_webhook(
+    State(state): State<AppState>,
+    user: Option<Extension<User>>,
+    Path(id): Path<u64>,
+) -> Response {
+    let owner = User::owner(&user);
+    let target = state.watches.read(|w| {
+        w.saved
+            .iter()
+            .find(|s| s.id == id && s.user == owner)
+            .and_then(|s| Some((s.webhook.clone()?, s.query.clone(), s.new_items.clone())))
+    });
+    let Some((hook, query, items)) = target else {
+        return Redirect::to("/saved").into_response();
+    };
+    if let Some(in_flight) = TestDelivery::start(&state, id) {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let delivery = webhook::deliver(&state, &hook, id, &query, &items, unix_now()).await;
+            log_delivery(&state, id, &owner, delivery);
+            drop(in_flight);
+        });
+    }
+    Redirect::to("/saved").into_response()
+}
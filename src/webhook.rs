@@ -0,0 +1,317 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use hmac::{Hmac, Mac};
+use maud::html;
+use reqwest::{
+    header::{CONTENT_TYPE, USER_AGENT},
+    redirect, Url,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tracing::{info, warn};
+use url::Host;
+
+use crate::{links, shutdown, watch::NewItem, AppState};
+
+/// First try plus retries.
+const MAX_ATTEMPTS: u32 = 4;
+
+/// Where a saved search posts its new results.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    pub format: Format,
+    /// Shared with the receiver to verify `X-My-Search-Signature-256`.
+    pub secret: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Our own JSON with the saved search and the new items.
+    Generic,
+    /// Slack incoming webhooks.
+    Slack,
+    /// matrix-hookshot generic webhooks, which take `text` and `html`.
+    Matrix,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Generic, Format::Slack, Format::Matrix];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Format::Generic => "generic",
+            Format::Slack => "slack",
+            Format::Matrix => "matrix",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Format> {
+        Format::ALL.into_iter().find(|f| f.as_str() == s)
+    }
+}
+
+/// Outcome of one delivery, kept in the saved search's delivery log.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Delivery {
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+    pub items: usize,
+    pub attempts: u32,
+    /// Status of the last attempt, if the receiver answered at all.
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl Delivery {
+    pub fn ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Only plain web URLs; anything else is refused when the webhook is set.
+///
+/// The server makes these requests, not the user's browser, so unless
+/// `allow_private` is set, hosts on loopback, private and link-local addresses
+/// are refused too. Names are checked again when delivering, see [`reachable`].
+pub fn valid_url(url: &str, allow_private: bool) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    match url.host() {
+        None => false,
+        Some(_) if allow_private => true,
+        Some(Host::Domain(name)) => {
+            let name = name.trim_end_matches('.').to_ascii_lowercase();
+            name != "localhost" && !name.ends_with(".localhost")
+        }
+        Some(Host::Ipv4(ip)) => !internal(ip.into()),
+        Some(Host::Ipv6(ip)) => !internal(ip.into()),
+    }
+}
+
+/// Addresses a webhook must not reach without `webhook_allow_private`.
+fn internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => internal_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => internal_v4(v4),
+            None => {
+                let [first, second, ..] = ip.segments();
+                ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // Unique local (fc00::/7) and link-local (fe80::/10).
+                    || first & 0xfe00 == 0xfc00
+                    || first & 0xffc0 == 0xfe80
+                    // NAT64 (64:ff9b::/96 and the local-use 64:ff9b:1::/48)
+                    // and 6to4 (2002::/16) embed an IPv4 address a gateway
+                    // will forward to, private or not.
+                    || (first == 0x64 && second == 0xff9b)
+                    || first == 0x2002
+            }
+        },
+    }
+}
+
+fn internal_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        // Shared address space (100.64.0.0/10), used by carrier-grade NAT.
+        || (a == 100 && b & 0xc0 == 64)
+}
+
+/// Resolves the webhook's host and checks that none of its addresses are
+/// internal, since a public name can point anywhere. Returns a client pinned
+/// to the checked addresses, so the name can't change in between and send
+/// the request elsewhere.
+async fn reachable(url: &str) -> Result<reqwest::Client, String> {
+    let url = Url::parse(url).map_err(|e| e.to_string())?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    let (name, addrs): (_, Vec<SocketAddr>) = match url.host() {
+        Some(Host::Domain(name)) => (
+            Some(name),
+            tokio::net::lookup_host((name, port))
+                .await
+                .map_err(|e| format!("could not resolve receiver: {e}"))?
+                .collect(),
+        ),
+        Some(Host::Ipv4(ip)) => (None, vec![(ip, port).into()]),
+        Some(Host::Ipv6(ip)) => (None, vec![(ip, port).into()]),
+        None => return Err("webhook URL has no host".into()),
+    };
+    if addrs.iter().any(|a| internal(a.ip())) {
+        return Err("receiver is on a private address".into());
+    }
+
+    let mut client = reqwest::Client::builder().redirect(redirect::Policy::none());
+    if let Some(name) = name {
+        client = client.resolve_to_addrs(name, &addrs);
+    }
+    client
+        .build()
+        .map_err(|e| format!("could not set up the request: {e}"))
+}
+
+fn payload(format: Format, id: u64, query: &str, items: &[NewItem], clean_links: bool) -> Value {
+    let url = |item: &NewItem| links::outbound(&item.html_url, clean_links);
+    let heading = format!("{} new results for \"{query}\"", items.len());
+    match format {
+        Format::Generic => json!({
+            "event": "new_results",
+            "saved_search": { "id": id, "query": query },
+            "items": items.iter().map(|item| json!({
+                "id": item.id,
+                "title": item.title,
+                "url": url(item),
+            })).collect::<Vec<_>>(),
+        }),
+        Format::Slack => {
+            // Slack's mrkdwn only needs these three escaped.
+            let esc = |s: &str| {
+                s.replace('&', "&amp;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;")
+            };
+            let mut text = esc(&heading);
+            for item in items {
+                match url(item) {
+                    Some(u) => text.push_str(&format!("\n• <{}|{}>", esc(&u), esc(&item.title))),
+                    None => text.push_str(&format!("\n• {}", esc(&item.title))),
+                }
+            }
+            json!({ "text": text })
+        }
+        Format::Matrix => {
+            let mut text = heading.clone();
+            for item in items {
+                text.push_str(&format!("\n- {}", item.title));
+                if let Some(u) = url(item) {
+                    text.push_str(&format!(" {u}"));
+                }
+            }
+            let html = html! {
+                p { (heading) }
+                ul {
+                    @for item in items {
+                        li {
+                            @match url(item) {
+                                Some(u) => a href=(u) { (item.title) },
+                                None => (item.title),
+                            }
+                        }
+                    }
+                }
+            };
+            json!({ "text": text, "html": html.into_string() })
+        }
+    }
+}
+
+/// `sha256=<hex>` HMAC of the body, the same scheme GitHub uses for its hooks.
+fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("any key length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Posts `items` to the webhook, retrying network errors, 429 and 5xx with
+/// exponential backoff. Other 4xx answers mean the request is wrong and are
+/// not retried, nor are redirects, which aren't followed.
+pub async fn deliver(
+    state: &AppState,
+    webhook: &Webhook,
+    id: u64,
+    query: &str,
+    items: &[NewItem],
+    at: u64,
+) -> Delivery {
+    let body = serde_json::to_vec(&payload(
+        webhook.format,
+        id,
+        query,
+        items,
+        state.config.clean_links,
+    ))
+    .unwrap();
+    let delivery_id = uuid::Uuid::new_v4().to_string();
+    let mut delay = state.config.webhook_retry_delay;
+    let mut delivery = Delivery {
+        at,
+        items: items.len(),
+        attempts: 0,
+        status: None,
+        error: None,
+    };
+
+    let client = if state.config.webhook_allow_private {
+        state.webhook_client.clone()
+    } else {
+        match reachable(&webhook.url).await {
+            Ok(client) => client,
+            Err(e) => {
+                warn!(id, error = %e, "webhook not delivered");
+                delivery.error = Some(e);
+                return delivery;
+            }
+        }
+    };
+
+    while delivery.attempts < MAX_ATTEMPTS {
+        if delivery.attempts > 0 {
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = shutdown::ABORT.cancelled() => break,
+            }
+            delay *= 2;
+        }
+        delivery.attempts += 1;
+
+        let mut req = client
+            .post(&webhook.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(USER_AGENT, "my-search")
+            .header("x-my-search-event", "new_results")
+            .header("x-my-search-delivery", &delivery_id)
+            .timeout(Duration::from_secs(10))
+            .body(body.clone());
+        if let Some(secret) = &webhook.secret {
+            req = req.header("x-my-search-signature-256", signature(secret, &body));
+        }
+
+        match req.send().await {
+            Ok(resp) => {
+                let status = resp.status();
+                delivery.status = Some(status.as_u16());
+                if status.is_success() {
+                    delivery.error = None;
+                    info!(id, attempts = delivery.attempts, "webhook delivered");
+                    return delivery;
+                }
+                delivery.error = Some(format!("receiver answered {status}"));
+                if status.is_redirection() || (status.is_client_error() && status.as_u16() != 429) {
+                    break;
+                }
+            }
+            Err(e) => {
+                delivery.status = None;
+                delivery.error = Some(format!("could not reach receiver: {}", e.without_url()));
+            }
+        }
+    }
+    warn!(id, attempts = delivery.attempts, error = ?delivery.error, "webhook delivery failed");
+    delivery
+}
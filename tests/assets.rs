@@ -0,0 +1,132 @@
+mod common;
+
+use axum::http::{header, StatusCode};
+use common::*;
+
+async fn stylesheet_uri(app: &axum::Router) -> String {
+    let page = get(app, "/").await;
+    stylesheet_href(&page.body).to_owned()
+}
+
+/// The stylesheet `href` from a rendered page.
+fn stylesheet_href(body: &str) -> &str {
+    let start = body.find(r#"rel="stylesheet" href=""#).unwrap() + 23;
+    let len = body[start..].find('"').unwrap();
+    &body[start..start + len]
+}
+
+#[tokio::test]
+async fn linked_stylesheet_is_fingerprinted_and_served() {
+    let app = test_app().await;
+    let page = get(&app, "/").await;
+    let href = stylesheet_href(&page.body);
+
+    let name = href.rsplit('/').next().unwrap();
+    assert!(name.starts_with("base.") && name.ends_with(".css") && name != "base.css");
+
+    assert!(href.starts_with("/static/"));
+
+    let css = get(&app, href).await;
+    assert_eq!(css.status, StatusCode::OK);
+    assert_eq!(css.headers[header::CONTENT_TYPE], "text/css");
+    assert!(css.body.contains(".logo-text"));
+}
+
+#[tokio::test]
+async fn stylesheet_has_the_tailwind_utilities() {
+    let app = test_app().await;
+    let uri = stylesheet_uri(&app).await;
+    let css = get(&app, &uri).await;
+
+    for utility in [
+        ".min-h-screen",
+        ".flex",
+        ".text-white",
+        ".bg-slate-800\\/50",
+    ] {
+        assert!(css.body.contains(utility), "missing {utility}");
+    }
+}
+
+#[tokio::test]
+async fn fingerprinted_assets_are_immutable_with_etag() {
+    let app = test_app().await;
+    let uri = stylesheet_uri(&app).await;
+
+    let css = get(&app, &uri).await;
+    assert_eq!(
+        css.headers[header::CACHE_CONTROL],
+        "public, max-age=31536000, immutable"
+    );
+    let etag = css.headers[header::ETAG].to_str().unwrap();
+    assert!(etag.starts_with('"') && etag.ends_with('"'));
+
+    let revalidated = get_with(&app, &uri, &[("if-none-match", etag)]).await;
+    assert_eq!(revalidated.status, StatusCode::NOT_MODIFIED);
+    assert!(revalidated.body.is_empty());
+    assert_eq!(revalidated.headers[header::ETAG], etag);
+
+    let changed = get_with(&app, &uri, &[("if-none-match", "\"stale\"")]).await;
+    assert_eq!(changed.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn text_responses_are_compressed() {
+    let app = test_app().await;
+
+    let page = get_with(&app, &q("box error"), &[("accept-encoding", "br, gzip")]).await;
+    assert_eq!(page.headers[header::CONTENT_ENCODING], "br");
+
+    let uri = stylesheet_uri(&app).await;
+    let css = get_with(&app, &uri, &[("accept-encoding", "gzip")]).await;
+    assert_eq!(css.headers[header::CONTENT_ENCODING], "gzip");
+
+    let plain = get(&app, &uri).await;
+    assert!(!plain.headers.contains_key(header::CONTENT_ENCODING));
+}
+
+#[tokio::test]
+async fn nested_routes_link_the_same_absolute_stylesheet() {
+    let app = test_app().await;
+    let root = get(&app, "/").await;
+    let nested = get(&app, "/some/nested/page").await;
+
+    assert_eq!(nested.status, StatusCode::NOT_FOUND);
+    assert_eq!(stylesheet_href(&nested.body), stylesheet_href(&root.body));
+}
+
+#[tokio::test]
+async fn traversal_is_rejected() {
+    let app = test_app().await;
+
+    for uri in [
+        "/static/../Cargo.toml",
+        "/static/..%2fCargo.toml",
+        "/static/%2e%2e/%2e%2e/Cargo.toml",
+        "/static/..%5c..%5cCargo.toml",
+        "/static//etc/passwd",
+        "/static/%252e%252e/Cargo.toml",
+    ] {
+        let resp = get(&app, uri).await;
+        assert!(
+            resp.status == StatusCode::BAD_REQUEST || resp.status == StatusCode::NOT_FOUND,
+            "{uri} -> {}",
+            resp.status
+        );
+        assert!(!resp.body.contains("[package]"), "{uri} leaked a file");
+    }
+
+    let resp = get(&app, "/static/..%2fCargo.toml").await;
+    assert_eq!(resp.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn missing_assets_are_a_bare_404() {
+    let app = test_app().await;
+
+    for uri in ["/static/missing.css", "/static/js/app.js"] {
+        let resp = get(&app, uri).await;
+        assert_eq!(resp.status, StatusCode::NOT_FOUND);
+        assert!(!resp.body.contains("<html") && !resp.body.contains("<!DOCTYPE"));
+    }
+}
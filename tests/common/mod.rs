@@ -0,0 +1,247 @@
+//! Shared harness: a local stub standing in for the GitHub API, replaying the
+//! recorded responses in `tests/fixtures`, and helpers to drive the app router.
+
+#![allow(dead_code)]
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Query, State},
+    http::{header, HeaderMap, Request, StatusCode},
+    response::{IntoResponse, Response},
+    routing, Router,
+};
+use my_search::{app, config::Config, AppState};
+use serde::Deserialize;
+use tower::ServiceExt;
+
+/// Queries the stub understands; anything else gets the success fixture.
+pub const Q_EMPTY: &str = "nothing matches this";
+pub const Q_RATE_LIMITED: &str = "rate limited";
+pub const Q_MALFORMED: &str = "malformed";
+pub const Q_HTML_ERROR: &str = "unicorn";
+pub const Q_INVALID: &str = "invalid";
+pub const Q_LINKS: &str = "suspicious links";
+/// No results on the first call, the success fixture afterwards.
+pub const Q_GROWING: &str = "growing";
+/// The success fixture, then only its first item, then both again.
+pub const Q_FLAPPING: &str = "flapping";
+/// `PAGED_TOTAL` generated issues, served a page at a time like GitHub does.
+pub const Q_PAGED: &str = "paged";
+pub const PAGED_TOTAL: usize = 250;
+/// Reports `POPULAR_TOTAL` matches but, like GitHub, only serves the first
+/// 1000 and answers 422 for pages past them.
+pub const Q_POPULAR: &str = "popular";
+pub const POPULAR_TOTAL: usize = 5000;
+
+macro_rules! fixture {
+    ($name:literal) => {
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/github/",
+            $name
+        ))
+    };
+}
+
+#[derive(Deserialize)]
+struct StubSearch {
+    q: String,
+    page: Option<usize>,
+    per_page: Option<usize>,
+}
+
+fn paged(page: usize, per_page: usize, total: usize) -> String {
+    let items: Vec<_> = ((page - 1) * per_page..(page * per_page).min(total))
+        .map(|i| {
+            serde_json::json!({
+                "url": format!("https://api.github.com/repos/example/app/issues/{i}"),
+                "repository_url": "https://api.github.com/repos/example/app",
+                "html_url": format!("https://github.com/example/app/issues/{i}"),
+                "id": i,
+                "title": format!("Issue {i}"),
+                "labels": [],
+                "state": "open",
+                "created_at": "2025-01-01T00:00:00Z",
+                "updated_at": "2025-01-01T00:00:00Z",
+                "body": null,
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "total_count": total,
+        "incomplete_results": false,
+        "items": items,
+    })
+    .to_string()
+}
+
+/// Token the stub rejects, as GitHub does for revoked ones.
+pub const REVOKED_TOKEN: &str = "revoked";
+
+async fn stub_search(
+    State(calls): State<Arc<AtomicUsize>>,
+    headers: HeaderMap,
+    Query(search): Query<StubSearch>,
+) -> Response {
+    let json = [(header::CONTENT_TYPE, "application/json; charset=utf-8")];
+    if headers
+        .get(header::AUTHORIZATION)
+        .is_some_and(|v| v == format!("Bearer {REVOKED_TOKEN}").as_str())
+    {
+        return (
+            StatusCode::UNAUTHORIZED,
+            json,
+            r#"{"message":"Bad credentials"}"#,
+        )
+            .into_response();
+    }
+    match search.q.as_str() {
+        Q_EMPTY => (json, fixture!("search_empty.json")).into_response(),
+        Q_RATE_LIMITED => (
+            StatusCode::FORBIDDEN,
+            [
+                (header::CONTENT_TYPE, "application/json; charset=utf-8"),
+                (
+                    header::HeaderName::from_static("x-ratelimit-remaining"),
+                    "0",
+                ),
+                (header::HeaderName::from_static("retry-after"), "42"),
+            ],
+            fixture!("rate_limited.json"),
+        )
+            .into_response(),
+        Q_MALFORMED => (json, fixture!("malformed.json")).into_response(),
+        Q_LINKS => (json, fixture!("search_links.json")).into_response(),
+        Q_PAGED => (
+            json,
+            paged(
+                search.page.unwrap_or(1),
+                search.per_page.unwrap_or(30),
+                PAGED_TOTAL,
+            ),
+        )
+            .into_response(),
+        Q_POPULAR => {
+            let (page, per_page) = (search.page.unwrap_or(1), search.per_page.unwrap_or(30));
+            if (page - 1) * per_page >= 1000 {
+                return (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    json,
+                    r#"{"message":"Only the first 1000 search results are available"}"#,
+                )
+                    .into_response();
+            }
+            (json, paged(page, per_page, POPULAR_TOTAL)).into_response()
+        }
+        Q_GROWING if calls.fetch_add(1, Ordering::SeqCst) == 0 => {
+            (json, fixture!("search_empty.json")).into_response()
+        }
+        Q_FLAPPING if calls.fetch_add(1, Ordering::SeqCst) == 1 => {
+            let mut root: serde_json::Value =
+                serde_json::from_str(fixture!("search_success.json")).unwrap();
+            root["items"].as_array_mut().unwrap().truncate(1);
+            root["total_count"] = 1.into();
+            (json, root.to_string()).into_response()
+        }
+        Q_HTML_ERROR => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+            fixture!("unicorn.html"),
+        )
+            .into_response(),
+        Q_INVALID => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            json,
+            r#"{"message":"Validation Failed"}"#,
+        )
+            .into_response(),
+        _ => (json, fixture!("search_success.json")).into_response(),
+    }
+}
+
+/// Starts the stub on an ephemeral port and returns its base URL.
+pub async fn stub_github() -> String {
+    let stub = Router::new()
+        .route("/search/issues", routing::get(stub_search))
+        .with_state(Arc::new(AtomicUsize::new(0)));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, stub).await.unwrap() });
+    format!("http://{addr}")
+}
+
+/// The real router, wired to a fresh stub.
+pub async fn test_app() -> Router {
+    test_app_with(|_| {}).await
+}
+
+/// Like [`test_app`], with the configuration adjusted by `configure` first.
+pub async fn test_app_with(configure: impl FnOnce(&mut Config)) -> Router {
+    let mut config = Config {
+        github_api_url: stub_github().await,
+        ..Config::default()
+    };
+    configure(&mut config);
+    app(AppState::new(config))
+}
+
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+pub async fn get(app: &Router, uri: &str) -> TestResponse {
+    get_with(app, uri, &[]).await
+}
+
+pub async fn get_with(app: &Router, uri: &str, headers: &[(&str, &str)]) -> TestResponse {
+    let mut req = Request::get(uri);
+    for (name, value) in headers {
+        req = req.header(*name, *value);
+    }
+    send(app, req.body(Body::empty()).unwrap()).await
+}
+
+/// POSTs an url-encoded form.
+pub async fn post_form(
+    app: &Router,
+    uri: &str,
+    form: &str,
+    headers: &[(&str, &str)],
+) -> TestResponse {
+    let mut req =
+        Request::post(uri).header(header::CONTENT_TYPE, "application/x-www-form-urlencoded");
+    for (name, value) in headers {
+        req = req.header(*name, *value);
+    }
+    send(app, req.body(Body::from(form.to_owned())).unwrap()).await
+}
+
+pub async fn send(app: &Router, req: Request<Body>) -> TestResponse {
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let headers = resp.headers().clone();
+    let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    TestResponse {
+        status,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    }
+}
+
+/// The `name=value` part of the response's Set-Cookie header.
+pub fn cookie(resp: &TestResponse) -> String {
+    let set_cookie = resp.headers[header::SET_COOKIE].to_str().unwrap();
+    set_cookie.split(';').next().unwrap().to_owned()
+}
+
+/// Search URI for `query`; test queries only need spaces encoded.
+pub fn q(query: &str) -> String {
+    format!("/?q={}", query.replace(' ', "+"))
+}
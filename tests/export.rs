@@ -0,0 +1,149 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Request, StatusCode},
+};
+use common::*;
+use my_search::config::Rate;
+use serde_json::Value;
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn csv_download() {
+    let app = test_app().await;
+    let resp = get(&app, "/export?q=box+error&format=csv").await;
+    assert_eq!(resp.status, StatusCode::OK);
+    assert_eq!(
+        resp.headers[header::CONTENT_TYPE],
+        "text/csv; charset=utf-8"
+    );
+    assert_eq!(
+        resp.headers[header::CONTENT_DISPOSITION],
+        r#"attachment; filename="search-results.csv""#
+    );
+
+    let lines: Vec<&str> = resp.body.split("\r\n").collect();
+    assert_eq!(
+        lines,
+        [
+            "title,url,source,repository,state,labels,created_at,updated_at",
+            "Box<dyn Error> loses backtrace,https://github.com/rust-lang/rust/issues/1001,github,rust-lang/rust,open,\"C-bug, A-error-handling\",2025-04-01T10:00:00Z,2025-05-02T12:30:00Z",
+            "Returning <Box> errors from handlers,https://github.com/tokio-rs/axum/issues/2002,github,tokio-rs/axum,closed,,2025-03-11T08:15:00Z,2025-03-12T09:00:00Z",
+            "",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn api_formats() {
+    let app = test_app().await;
+
+    let resp = get(&app, "/api/search?q=box+error&format=jsonl").await;
+    assert_eq!(resp.status, StatusCode::OK);
+    assert!(!resp.headers.contains_key(header::CONTENT_DISPOSITION));
+    let rows: Vec<Value> = resp
+        .body
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["title"], "Box<dyn Error> loses backtrace");
+    assert_eq!(rows[0]["labels"][1], "A-error-handling");
+    assert_eq!(rows[1]["state"], "closed");
+
+    let resp = get(&app, "/api/search?q=box+error&format=markdown").await;
+    assert_eq!(
+        resp.body,
+        "- [Box\\<dyn Error\\> loses backtrace](https://github.com/rust-lang/rust/issues/1001) · rust-lang/rust · open · C-bug, A-error-handling · updated 2025-05-02\n\
+         - [Returning \\<Box\\> errors from handlers](https://github.com/tokio-rs/axum/issues/2002) · tokio-rs/axum · closed · updated 2025-03-12\n"
+    );
+
+    // JSON stays the default.
+    let resp = get(&app, "/api/search?q=box+error&format=json").await;
+    assert_eq!(resp.headers[header::CONTENT_TYPE], "application/json");
+
+    let resp = get(&app, "/api/search?q=box+error&format=xlsx").await;
+    assert_eq!(resp.status, StatusCode::BAD_REQUEST);
+    assert!(resp.body.contains(r#"unknown export format \"xlsx\""#));
+}
+
+#[tokio::test]
+async fn all_pages_up_to_the_limit() {
+    let app = test_app().await;
+    let resp = get(&app, &format!("/api/search?q={Q_PAGED}&format=jsonl")).await;
+    let lines: Vec<&str> = resp.body.lines().collect();
+    assert_eq!(lines.len(), PAGED_TOTAL);
+    assert!(lines[PAGED_TOTAL - 1].contains(r#""title":"Issue 249""#));
+
+    let app = test_app_with(|c| c.export_limit = 150).await;
+    let resp = get(&app, &format!("/export?q={Q_PAGED}&format=markdown")).await;
+    assert_eq!(resp.body.lines().count(), 150);
+}
+
+#[tokio::test]
+async fn exports_stop_where_github_stops_serving_results() {
+    let app = test_app_with(|c| c.export_limit = 2000).await;
+    let resp = get(&app, &format!("/api/search?q={Q_POPULAR}&format=jsonl")).await;
+    assert_eq!(resp.status, StatusCode::OK);
+    let lines: Vec<&str> = resp.body.lines().collect();
+    assert_eq!(lines.len(), 1000);
+    assert!(lines[999].contains(r#""title":"Issue 999""#));
+}
+
+#[tokio::test]
+async fn every_page_counts_against_the_rate_limit() {
+    let limited = |burst| {
+        test_app_with(move |c| {
+            c.search_rate_limit = Some(Rate {
+                burst,
+                per: Duration::from_secs(60),
+            })
+        })
+    };
+
+    // Three pages take three tokens, leaving none for the next search.
+    let app = limited(3).await;
+    let resp = get(&app, &format!("/export?q={Q_PAGED}&format=jsonl")).await;
+    assert_eq!(resp.body.lines().count(), PAGED_TOTAL);
+    assert_eq!(
+        get(&app, &q("box error")).await.status,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+
+    // Running out part way cuts the download short.
+    let app = limited(2).await;
+    let req = Request::get(format!("/export?q={Q_PAGED}&format=jsonl"))
+        .body(Body::empty())
+        .unwrap();
+    let resp = app.oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(to_bytes(resp.into_body(), usize::MAX).await.is_err());
+}
+
+#[tokio::test]
+async fn unsafe_links_are_dropped_and_errors_returned() {
+    let app = test_app().await;
+    let resp = get(
+        &app,
+        &format!("/export?q={}&format=csv", Q_LINKS.replace(' ', "+")),
+    )
+    .await;
+    // The `javascript:` link is dropped, not exported.
+    assert!(resp.body.contains("\r\nScripted link,,github,example/app,"));
+
+    let resp = get(&app, &format!("/export?q={Q_INVALID}")).await;
+    assert_eq!(resp.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn results_page_links_to_exports() {
+    let app = test_app().await;
+    let resp = get(&app, &q("box error")).await;
+    assert!(resp
+        .body
+        .contains(r#"href="/export?q=box+error&amp;format=csv""#));
+    assert!(resp.body.contains("JSON Lines"));
+}
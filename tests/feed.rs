@@ -0,0 +1,97 @@
+mod common;
+
+use axum::http::{header, StatusCode};
+use common::*;
+
+const HOST: (&str, &str) = ("host", "search.example");
+
+#[tokio::test]
+async fn atom_feed_is_escaped_and_dated() {
+    let app = test_app().await;
+    let resp = get_with(&app, "/feed?q=box+error", &[HOST]).await;
+
+    assert_eq!(resp.status, StatusCode::OK);
+    assert_eq!(
+        resp.headers[header::CONTENT_TYPE],
+        "application/atom+xml; charset=utf-8"
+    );
+    let xml = &resp.body;
+    assert!(xml.starts_with(
+        r#"<?xml version="1.0" encoding="utf-8"?><feed xmlns="http://www.w3.org/2005/Atom">"#
+    ));
+    assert!(xml.contains("<title>My Search: box error</title>"));
+    assert!(xml.contains("<author><name>My Search</name></author>"));
+    assert!(xml.contains(r#"<link rel="self" href="http://search.example/feed?q=box+error"/>"#));
+    // The newest item's update time, right before the first entry.
+    assert!(xml.contains("<updated>2025-05-02T12:30:00Z</updated><entry>"));
+    assert!(xml.contains("<title>Box&lt;dyn Error&gt; loses backtrace</title>"));
+    assert!(xml.contains("<id>https://api.github.com/repos/rust-lang/rust/issues/1001</id>"));
+    assert!(xml.contains("<published>2025-04-01T10:00:00Z</published>"));
+    assert!(xml.contains(
+        "<summary>Converting into `Box&lt;dyn Error&gt;` drops the backtrace.</summary>"
+    ));
+    assert!(!xml.contains("<dyn"));
+}
+
+#[tokio::test]
+async fn rss_feed_uses_rfc_2822_dates() {
+    let app = test_app().await;
+    let resp = get_with(&app, "/feed?q=box+error&format=rss", &[HOST]).await;
+
+    assert_eq!(resp.status, StatusCode::OK);
+    assert_eq!(
+        resp.headers[header::CONTENT_TYPE],
+        "application/rss+xml; charset=utf-8"
+    );
+    let xml = &resp.body;
+    assert!(xml.contains("<link>http://search.example/?q=box+error</link>"));
+    assert!(xml.contains("<lastBuildDate>Fri, 02 May 2025 12:30:00 +0000</lastBuildDate>"));
+    assert!(xml.contains(r#"<guid isPermaLink="false">https://api.github.com/repos/rust-lang/rust/issues/1001</guid>"#));
+    assert!(xml.contains("<pubDate>Wed, 12 Mar 2025 09:00:00 +0000</pubDate>"));
+}
+
+#[tokio::test]
+async fn feed_links_are_validated() {
+    let app = test_app().await;
+    let resp = get(&app, &format!("/feed?q={}", Q_LINKS.replace(' ', "+"))).await;
+
+    assert!(resp.body.contains("<title>Scripted link</title>"));
+    // Atom needs content when there is no link to the entry.
+    let entry = resp.body.split("<entry>").nth(1).unwrap();
+    assert!(!entry.contains("<link"));
+    assert!(entry.contains("<content>Scripted link</content>"));
+    assert!(!resp.body.contains("javascript:"));
+    assert!(!resp.body.contains("data:text/html"));
+}
+
+#[tokio::test]
+async fn feeds_are_cached() {
+    let app = test_app().await;
+
+    let first = get(&app, "/feed?q=growing").await;
+    assert!(!first.body.contains("<entry>"));
+
+    // The page searches again and sees the new items; the feed reuses its results.
+    let page = get(&app, "/?q=growing").await;
+    assert!(page.body.contains("Results (2)"));
+    let second = get(&app, "/feed?q=growing").await;
+    assert!(!second.body.contains("<entry>"));
+
+    let metrics = get(&app, "/metrics").await;
+    assert!(metrics
+        .body
+        .contains(r#"my_search_cache_lookups_total{cache="feed",outcome="hit"}"#));
+    assert!(metrics
+        .body
+        .contains(r#"my_search_cache_lookups_total{cache="feed",outcome="miss"}"#));
+}
+
+#[tokio::test]
+async fn unknown_formats_are_rejected() {
+    let app = test_app().await;
+    let resp = get(&app, "/feed?q=box&format=json").await;
+    assert_eq!(resp.status, StatusCode::BAD_REQUEST);
+
+    let page = get(&app, &q("box error")).await;
+    assert!(page.body.contains(r#"href="/feed?q=box+error""#));
+}
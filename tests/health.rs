@@ -0,0 +1,52 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::*;
+use serde_json::Value;
+
+#[tokio::test]
+async fn healthz_is_always_ok() {
+    let app = test_app().await;
+    let resp = get(&app, "/healthz").await;
+
+    assert_eq!(resp.status, StatusCode::OK);
+    assert_eq!(resp.body, "ok");
+}
+
+#[tokio::test]
+async fn readiness_follows_provider_health() {
+    let app = test_app().await;
+
+    // Rate limits and a single bad response are not outages.
+    for _ in 0..3 {
+        get(&app, &q(Q_RATE_LIMITED)).await;
+    }
+    get(&app, &q(Q_HTML_ERROR)).await;
+    let resp = get(&app, "/readyz").await;
+    assert_eq!(resp.status, StatusCode::OK);
+
+    get(&app, &q(Q_HTML_ERROR)).await;
+    get(&app, &q(Q_HTML_ERROR)).await;
+    let resp = get(&app, "/readyz").await;
+    assert_eq!(resp.status, StatusCode::SERVICE_UNAVAILABLE);
+    let json: Value = serde_json::from_str(&resp.body).unwrap();
+    assert_eq!(json["ready"], false);
+    assert_eq!(json["providers"]["github"], false);
+
+    get(&app, &q("box error")).await;
+    let resp = get(&app, "/readyz").await;
+    assert_eq!(resp.status, StatusCode::OK);
+    let json: Value = serde_json::from_str(&resp.body).unwrap();
+    assert_eq!(json["ready"], true);
+    assert_eq!(json["providers"]["github"], true);
+}
+
+#[tokio::test]
+async fn status_page_lists_providers() {
+    let app = test_app().await;
+    let resp = get(&app, "/status").await;
+
+    assert_eq!(resp.status, StatusCode::OK);
+    assert!(resp.body.contains("Provider Status"));
+    assert!(resp.body.contains("github"));
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::*;
+use my_search::{config::Config, AppState};
+
+#[tokio::test]
+async fn searches_are_listed_newest_first() {
+    let app = test_app().await;
+    get(&app, &q("box error")).await;
+    get(&app, &q("!gh panic hook")).await;
+    // Failed searches aren't recorded.
+    get(&app, &q(Q_INVALID)).await;
+
+    let resp = get(&app, "/history").await;
+    assert_eq!(resp.status, StatusCode::OK);
+    let newest = resp.body.find("!gh panic hook").unwrap();
+    let oldest = resp.body.find("box error").unwrap();
+    assert!(newest < oldest);
+    assert!(resp.body.contains("github · 2 results · 0s ago"));
+    assert!(resp.body.contains(r#"href="/?q=%21gh+panic+hook""#));
+    assert!(!resp.body.contains(Q_INVALID));
+
+    let filtered = get(&app, "/history?filter=PANIC").await;
+    assert!(filtered.body.contains("!gh panic hook"));
+    assert!(!filtered.body.contains("box error"));
+}
+
+#[tokio::test]
+async fn entries_can_be_deleted_and_cleared() {
+    let app = test_app().await;
+    get(&app, &q("box error")).await;
+    get(&app, &q("panic hook")).await;
+    get(&app, &q("async drop")).await;
+
+    let resp = post_form(&app, "/history/0/delete", "", &[]).await;
+    assert_eq!(resp.status, StatusCode::SEE_OTHER);
+    let page = get(&app, "/history").await;
+    assert!(!page.body.contains("box error"));
+    assert!(page.body.contains("panic hook"));
+
+    post_form(&app, "/history/clear", "", &[]).await;
+    let page = get(&app, "/history").await;
+    assert!(page.body.contains("No searches recorded."));
+}
+
+#[tokio::test]
+async fn users_only_see_their_own_history() {
+    let app = test_app_with(|c| c.auth_proxy_header = Some("X-Forwarded-User".into())).await;
+    let alice = [("x-forwarded-user", "alice")];
+    let bob = [("x-forwarded-user", "bob")];
+
+    get_with(&app, &q("alice searches this"), &alice).await;
+    get_with(&app, &q("bob searches that"), &bob).await;
+
+    let page = get_with(&app, "/history", &alice).await;
+    assert!(page.body.contains("alice searches this"));
+    assert!(!page.body.contains("bob searches that"));
+
+    // Bob can't delete Alice's entry.
+    post_form(&app, "/history/0/delete", "", &bob).await;
+    let page = get_with(&app, "/history", &alice).await;
+    assert!(page.body.contains("alice searches this"));
+}
+
+#[tokio::test]
+async fn history_can_be_turned_off() {
+    let app = test_app().await;
+    let resp = post_form(&app, "/settings", "provider=github&new_tab=on", &[]).await;
+    let prefs = cookie(&resp);
+
+    get_with(&app, &q("box error"), &[("cookie", &prefs)]).await;
+
+    let page = get_with(&app, "/history", &[("cookie", &prefs)]).await;
+    assert!(page.body.contains("Recording is turned off"));
+    assert!(page.body.contains("No searches recorded."));
+}
+
+#[tokio::test]
+async fn history_is_saved_in_the_background_and_flushed() {
+    let dir = std::env::temp_dir().join(format!("my-search-history-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let config = || Config {
+        data_dir: Some(dir.clone()),
+        ..Config::default()
+    };
+    let state = AppState::new(Config {
+        github_api_url: stub_github().await,
+        ..config()
+    });
+    let app = my_search::app(state.clone());
+
+    // The search doesn't wait for the file to be written.
+    get(&app, &q("box error")).await;
+    assert!(!dir.join("history.json").exists());
+
+    state.flush();
+    let saved = std::fs::read_to_string(dir.join("history.json")).unwrap();
+    assert!(saved.contains("box error"));
+
+    let restarted = my_search::app(AppState::new(Config {
+        github_api_url: stub_github().await,
+        ..config()
+    }));
+    let page = get(&restarted, "/history").await;
+    assert!(page.body.contains("box error"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
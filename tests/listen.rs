@@ -0,0 +1,75 @@
+mod common;
+
+use std::os::unix::fs::PermissionsExt;
+
+use my_search::{
+    config::Config,
+    listen::{self, Listener},
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn serves_over_unix_socket_with_configured_mode() {
+    let path = std::env::temp_dir().join(format!("my-search-test-{}.sock", std::process::id()));
+    let config = Config {
+        unix_socket: Some(path.clone()),
+        unix_socket_mode: 0o600,
+        ..Config::default()
+    };
+
+    let Listener::Unix(listener, created) = listen::bind(&config).unwrap() else {
+        panic!("expected a Unix listener");
+    };
+    assert_eq!(created.as_deref(), Some(path.as_path()));
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    let listener = tokio::net::UnixListener::from_std(listener).unwrap();
+    let app = common::test_app().await;
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let mut stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+    stream
+        .write_all(b"GET /healthz HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut resp = String::new();
+    stream.read_to_string(&mut resp).await.unwrap();
+    assert!(resp.starts_with("HTTP/1.1 200 OK"));
+    assert!(resp.ends_with("ok"));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn stale_socket_file_is_replaced() {
+    let path = std::env::temp_dir().join(format!("my-search-stale-{}.sock", std::process::id()));
+    // Dropping a listener leaves its socket file behind, as a crash would.
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+    let config = Config {
+        unix_socket: Some(path.clone()),
+        ..Config::default()
+    };
+
+    assert!(matches!(listen::bind(&config).unwrap(), Listener::Unix(..)));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn other_files_at_the_socket_path_are_left_alone() {
+    let path = std::env::temp_dir().join(format!("my-search-file-{}.sock", std::process::id()));
+    std::fs::write(&path, b"precious").unwrap();
+    let config = Config {
+        unix_socket: Some(path.clone()),
+        ..Config::default()
+    };
+
+    let err = listen::bind(&config)
+        .err()
+        .expect("refused to replace a file");
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    assert_eq!(std::fs::read(&path).unwrap(), b"precious");
+
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use axum::http::{header, StatusCode};
+use common::*;
+
+#[tokio::test]
+async fn fragment_is_only_the_results() {
+    let app = test_app().await;
+    let resp = get(&app, "/results?q=box+error").await;
+    assert_eq!(resp.status, StatusCode::OK);
+    assert_eq!(resp.headers["x-fragment"], "results");
+    assert!(resp
+        .body
+        .starts_with(r#"<div class="flex items-center mb-4">"#));
+    assert!(resp.body.contains("Results (2)"));
+    assert!(resp.body.contains("Box&lt;dyn Error&gt; loses backtrace"));
+    assert!(!resp.body.contains("<head"));
+    assert!(!resp.body.contains(r#"id="results""#));
+
+    // The full page wraps the same markup in the element fragments replace.
+    let page = get(&app, &q("box error")).await;
+    assert!(page
+        .body
+        .contains(r#"<div id="results"><div class="flex items-center mb-4">"#));
+    assert!(page.body.contains("fetch('/results?' + params"));
+
+    // Typing isn't searching on purpose; only the full page is recorded.
+    let history = get(&app, "/history").await;
+    assert_eq!(history.body.matches("Run again").count(), 1);
+}
+
+#[tokio::test]
+async fn fragment_errors_are_fragments() {
+    let app = test_app().await;
+    let resp = get(&app, &format!("/results?q={Q_INVALID}")).await;
+    assert_eq!(resp.status, StatusCode::BAD_REQUEST);
+    assert_eq!(resp.headers["x-fragment"], "results");
+    assert!(resp.body.contains("Invalid Search"));
+    assert!(!resp.body.contains("<head"));
+}
+
+#[tokio::test]
+async fn typing_searches_sparingly() {
+    let page = get(&test_app().await, &q("box error")).await;
+    assert!(page
+        .body
+        .contains("if (q.length < MIN_CHARS || q === shown) return;"));
+    assert!(page.body.contains("DEBOUNCE_MS = 800"));
+
+    // A rate-limited search says so under the box instead of doing nothing.
+    assert!(page.body.contains("if (resp.status === 429) {"));
+    assert!(page
+        .body
+        .contains(r#"<p id="search-notice" class="text-red-400 text-sm mt-2" hidden></p>"#));
+}
+
+#[tokio::test]
+async fn signed_out_fragments_load_the_whole_page() {
+    let app = test_app_with(|c| {
+        c.basic_users = [("alice".to_owned(), "wonderland".to_owned())].into();
+    })
+    .await;
+
+    // fetch follows the redirect to the login page, which isn't a fragment,
+    // so the script loads the search as a page instead of swapping it in.
+    let resp = get(&app, "/results?q=box+error").await;
+    assert_eq!(resp.status, StatusCode::SEE_OTHER);
+    let login = get(&app, resp.headers[header::LOCATION].to_str().unwrap()).await;
+    assert_eq!(login.status, StatusCode::OK);
+    assert!(!login.headers.contains_key("x-fragment"));
+
+    let page = get(&test_app().await, &q("box error")).await;
+    assert!(page
+        .body
+        .contains("if (resp.redirected || resp.headers.get('x-fragment') !== 'results')"));
+    assert!(page.body.contains("location.href = '/?' + params;"));
+}
+
+#[tokio::test]
+async fn pages_link_to_each_other() {
+    let app = test_app().await;
+
+    let first = get(&app, &q(Q_PAGED)).await;
+    assert!(first.body.contains("Page 1 of 9"));
+    assert!(!first.body.contains("Previous"));
+    assert!(first
+        .body
+        .contains(r#"<a href="/?q=paged&amp;page=2" data-fragment"#));
+
+    let second = get(&app, &format!("/?q={Q_PAGED}&page=2")).await;
+    assert!(second.body.contains("Issue 30<"));
+    assert!(!second.body.contains("Issue 29<"));
+    assert!(second.body.contains(r#"href="/?q=paged&amp;page=1""#));
+    assert!(second.body.contains(r#"href="/?q=paged&amp;page=3""#));
+
+    let last = get(&app, &format!("/results?q={Q_PAGED}&page=9")).await;
+    assert!(last.body.contains("Page 9 of 9"));
+    assert!(!last.body.contains("Next"));
+
+    // Paging through is one search in the history.
+    let history = get(&app, "/history").await;
+    assert_eq!(history.body.matches("Run again").count(), 1);
+}
+
+#[tokio::test]
+async fn single_page_has_no_pager() {
+    let app = test_app().await;
+    let resp = get(&app, &q("box error")).await;
+    assert!(!resp.body.contains("Page 1 of"));
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use std::{net::SocketAddr, time::Duration};
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use common::*;
+use my_search::config::Rate;
+
+const TWO_PER_MINUTE: Rate = Rate {
+    burst: 2,
+    per: Duration::from_secs(60),
+};
+
+async fn limited_app(trust_forwarded_for: bool) -> Router {
+    test_app_with(|c| {
+        c.search_rate_limit = Some(TWO_PER_MINUTE);
+        c.api_rate_limit = Some(TWO_PER_MINUTE);
+        c.trust_forwarded_for = trust_forwarded_for;
+    })
+    .await
+}
+
+async fn from_peer(app: &Router, uri: &str, peer: &str) -> TestResponse {
+    let mut req = Request::get(uri).body(Body::empty()).unwrap();
+    let addr: SocketAddr = peer.parse().unwrap();
+    req.extensions_mut().insert(ConnectInfo(addr));
+    send(app, req).await
+}
+
+#[tokio::test]
+async fn html_searches_get_a_429_page() {
+    let app = limited_app(false).await;
+
+    for _ in 0..2 {
+        let resp = from_peer(&app, &q("box error"), "192.0.2.1:5000").await;
+        assert_eq!(resp.status, StatusCode::OK);
+    }
+    let resp = from_peer(&app, &q("box error"), "192.0.2.1:5001").await;
+    assert_eq!(resp.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(resp.headers[header::RETRY_AFTER], "30");
+    assert!(resp.body.contains("Slow Down"));
+    assert!(resp.body.contains("Try again in 30 seconds."));
+
+    // The landing page doesn't search, so it isn't limited.
+    let resp = from_peer(&app, "/", "192.0.2.1:5002").await;
+    assert_eq!(resp.status, StatusCode::OK);
+
+    // Other clients have their own bucket.
+    let resp = from_peer(&app, &q("box error"), "192.0.2.2:5000").await;
+    assert_eq!(resp.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn api_has_its_own_limit_and_json_errors() {
+    let app = limited_app(false).await;
+
+    for _ in 0..2 {
+        let resp = from_peer(&app, &q("box error"), "192.0.2.1:5000").await;
+        assert_eq!(resp.status, StatusCode::OK);
+    }
+    for _ in 0..2 {
+        let resp = from_peer(&app, "/api/search?q=box", "192.0.2.1:5000").await;
+        assert_eq!(resp.status, StatusCode::OK);
+    }
+    let resp = from_peer(&app, "/api/search?q=box", "192.0.2.1:5000").await;
+    assert_eq!(resp.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(resp.headers[header::RETRY_AFTER], "30");
+    let json: serde_json::Value = serde_json::from_str(&resp.body).unwrap();
+    assert_eq!(json["error"], "too_many_requests");
+}
+
+#[tokio::test]
+async fn signed_in_users_are_limited_by_name() {
+    let app = test_app_with(|c| {
+        c.search_rate_limit = Some(TWO_PER_MINUTE);
+        c.auth_proxy_header = Some("X-Forwarded-User".into());
+    })
+    .await;
+
+    for _ in 0..2 {
+        get_with(&app, &q("box error"), &[("x-forwarded-user", "alice")]).await;
+    }
+    let alice = get_with(&app, &q("box error"), &[("x-forwarded-user", "alice")]).await;
+    assert_eq!(alice.status, StatusCode::TOO_MANY_REQUESTS);
+    let bob = get_with(&app, &q("box error"), &[("x-forwarded-user", "bob")]).await;
+    assert_eq!(bob.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn forwarded_for_is_only_trusted_when_configured() {
+    for trusted in [false, true] {
+        let app = limited_app(trusted).await;
+        for client in ["198.51.100.1", "198.51.100.2", "198.51.100.3"] {
+            let forwarded = format!("203.0.113.9, {client}");
+            let mut req = Request::get(q("box error"))
+                .header("x-forwarded-for", forwarded)
+                .body(Body::empty())
+                .unwrap();
+            req.extensions_mut()
+                .insert(ConnectInfo("192.0.2.1:5000".parse::<SocketAddr>().unwrap()));
+            let resp = send(&app, req).await;
+            let expected = if trusted || client != "198.51.100.3" {
+                StatusCode::OK
+            } else {
+                StatusCode::TOO_MANY_REQUESTS
+            };
+            assert_eq!(resp.status, expected, "trusted={trusted} client={client}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn saved_search_runs_share_the_search_limit() {
+    let app = test_app_with(|c| {
+        c.search_rate_limit = Some(TWO_PER_MINUTE);
+        c.auth_proxy_header = Some("X-Forwarded-User".into());
+    })
+    .await;
+    let alice = [("x-forwarded-user", "alice")];
+
+    let resp = post_form(&app, "/saved", "query=box+error", &alice).await;
+    assert_eq!(resp.status, StatusCode::SEE_OTHER);
+    let resp = post_form(&app, "/saved/0/run", "", &alice).await;
+    assert_eq!(resp.status, StatusCode::SEE_OTHER);
+    let resp = post_form(&app, "/saved/0/run", "", &alice).await;
+    assert_eq!(resp.status, StatusCode::TOO_MANY_REQUESTS);
+    let resp = get_with(&app, &q("box error"), &alice).await;
+    assert_eq!(resp.status, StatusCode::TOO_MANY_REQUESTS);
+
+    // Looking at the dashboard doesn't search.
+    let resp = get_with(&app, "/saved", &alice).await;
+    assert_eq!(resp.status, StatusCode::OK);
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use axum::http::{header, StatusCode};
+use common::*;
+use my_search::{config::Config, AppState};
+
+#[tokio::test]
+async fn new_results_show_up_on_the_dashboard() {
+    let app = test_app().await;
+
+    // Creating runs the search once to record what is already there.
+    let resp = post_form(&app, "/saved", "query=growing&interval_mins=15", &[]).await;
+    assert_eq!(resp.status, StatusCode::SEE_OTHER);
+    assert_eq!(resp.headers[header::LOCATION], "/saved");
+
+    let page = get(&app, "/saved").await;
+    assert!(page.body.contains(Q_GROWING));
+    assert!(page.body.contains("Every 15 minutes · last run 0s ago"));
+    assert!(!page.body.contains(" new<"));
+
+    post_form(&app, "/saved/0/run", "", &[]).await;
+    let page = get(&app, "/saved").await;
+    assert!(page.body.contains("2 new"));
+    assert!(page.body.contains("Box&lt;dyn Error&gt; loses backtrace"));
+
+    // The same results again are not new.
+    post_form(&app, "/saved/0/seen", "", &[]).await;
+    post_form(&app, "/saved/0/run", "", &[]).await;
+    let page = get(&app, "/saved").await;
+    assert!(!page.body.contains(" new<"));
+
+    post_form(&app, "/saved/0/delete", "", &[]).await;
+    let page = get(&app, "/saved").await;
+    assert!(page.body.contains("Nothing is being watched yet."));
+}
+
+#[tokio::test]
+async fn results_that_come_back_are_not_new() {
+    let app = test_app().await;
+    post_form(&app, "/saved", &format!("query={Q_FLAPPING}"), &[]).await;
+
+    // The second result drops out of the search, then shows up again.
+    post_form(&app, "/saved/0/run", "", &[]).await;
+    post_form(&app, "/saved/0/run", "", &[]).await;
+    let page = get(&app, "/saved").await;
+    assert!(!page.body.contains(" new<"));
+}
+
+#[tokio::test]
+async fn failures_are_shown() {
+    let app = test_app().await;
+    post_form(&app, "/saved", &format!("query={Q_INVALID}"), &[]).await;
+
+    let page = get(&app, "/saved").await;
+    assert!(page.body.contains("Every 60 minutes"));
+    assert!(page
+        .body
+        .contains("GitHub could not process this search query"));
+}
+
+#[tokio::test]
+async fn results_page_offers_to_watch() {
+    let app = test_app().await;
+    let resp = get(&app, &q("box error")).await;
+    assert!(resp
+        .body
+        .contains(r#"<form method="post" action="/saved">"#));
+    assert!(resp.body.contains(r#"name="query" value="box error""#));
+}
+
+#[tokio::test]
+async fn saved_searches_are_private() {
+    let app = test_app_with(|c| c.auth_proxy_header = Some("X-Forwarded-User".into())).await;
+    let alice = [("x-forwarded-user", "alice")];
+    let bob = [("x-forwarded-user", "bob")];
+
+    post_form(&app, "/saved", "query=alice+watches+this", &alice).await;
+    let page = get_with(&app, "/saved", &bob).await;
+    assert!(!page.body.contains("alice watches this"));
+
+    post_form(&app, "/saved/0/delete", "", &bob).await;
+    let page = get_with(&app, "/saved", &alice).await;
+    assert!(page.body.contains("alice watches this"));
+}
+
+#[tokio::test]
+async fn runs_are_saved_in_the_background_and_flushed() {
+    let dir = std::env::temp_dir().join(format!("my-search-saved-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let state = AppState::new(Config {
+        github_api_url: stub_github().await,
+        data_dir: Some(dir.clone()),
+        ..Config::default()
+    });
+    let app = my_search::app(state.clone());
+
+    // Creating it is written right away; the first run's outcome later.
+    post_form(&app, "/saved", "query=box+error", &[]).await;
+    let saved = std::fs::read_to_string(dir.join("saved.json")).unwrap();
+    assert!(saved.contains(r#""last_run": null"#));
+
+    state.flush();
+    let saved = std::fs::read_to_string(dir.join("saved.json")).unwrap();
+    assert!(saved.contains("box error"));
+    assert!(!saved.contains(r#""last_run": null"#));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
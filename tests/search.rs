@@ -0,0 +1,133 @@
+mod common;
+
+use axum::http::{header, StatusCode};
+use common::*;
+
+#[tokio::test]
+async fn home_page_renders_search_form() {
+    let app = test_app().await;
+    let resp = get(&app, "/").await;
+
+    assert_eq!(resp.status, StatusCode::OK);
+    assert!(resp.body.contains(r#"name="q""#));
+}
+
+#[tokio::test]
+async fn results_are_rendered_and_escaped() {
+    let app = test_app().await;
+    let resp = get(&app, &q("box error")).await;
+
+    assert_eq!(resp.status, StatusCode::OK);
+    assert!(resp.body.contains("Results (2)"));
+    assert!(resp.body.contains("Box&lt;dyn Error&gt; loses backtrace"));
+    assert!(resp
+        .body
+        .contains("https://github.com/tokio-rs/axum/issues/2002"));
+    assert!(!resp.body.contains("<Box>"));
+}
+
+#[tokio::test]
+async fn empty_results_show_message() {
+    let app = test_app().await;
+    let resp = get(&app, &q(Q_EMPTY)).await;
+
+    assert_eq!(resp.status, StatusCode::OK);
+    assert!(resp.body.contains("Results (0)"));
+    assert!(resp.body.contains("No results found"));
+}
+
+#[tokio::test]
+async fn rate_limit_maps_to_503_with_retry_after() {
+    let app = test_app().await;
+    let resp = get(&app, &q(Q_RATE_LIMITED)).await;
+
+    assert_eq!(resp.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(resp.headers[header::RETRY_AFTER], "42");
+    assert!(resp.body.contains("Rate Limit Reached"));
+    assert!(resp.body.contains("Try again in 42 seconds."));
+}
+
+#[tokio::test]
+async fn malformed_json_is_a_decode_error() {
+    let app = test_app().await;
+    let resp = get(&app, &q(Q_MALFORMED)).await;
+
+    assert_eq!(resp.status, StatusCode::BAD_GATEWAY);
+    assert!(resp.body.contains("Unexpected Response"));
+}
+
+#[tokio::test]
+async fn html_error_page_is_a_decode_error() {
+    let app = test_app().await;
+    let resp = get(&app, &q(Q_HTML_ERROR)).await;
+
+    assert_eq!(resp.status, StatusCode::BAD_GATEWAY);
+    assert!(resp.body.contains("expected JSON, got text/html"));
+    assert!(!resp.body.contains("Unicorn!"));
+}
+
+#[tokio::test]
+async fn rejected_query_is_a_bad_request() {
+    let app = test_app().await;
+
+    let resp = get(&app, &q(Q_INVALID)).await;
+    assert_eq!(resp.status, StatusCode::BAD_REQUEST);
+    assert!(resp.body.contains("Invalid Search"));
+
+    let resp = get(&app, "/?q=+").await;
+    assert_eq!(resp.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn result_links_are_validated() {
+    let app = test_app().await;
+    let resp = get(&app, &q(Q_LINKS)).await;
+
+    assert_eq!(resp.status, StatusCode::OK);
+    assert!(!resp.body.contains("javascript:"));
+    assert!(!resp.body.contains("data:text/html"));
+    assert_eq!(resp.body.matches("Link removed").count(), 2);
+    assert!(resp.body.contains(r#"rel="noopener noreferrer""#));
+    assert!(resp.body.contains("utm_source=feed"));
+}
+
+#[tokio::test]
+async fn clean_links_strip_tracking_parameters() {
+    let app = test_app_with(|c| c.clean_links = true).await;
+    let resp = get(&app, &q(Q_LINKS)).await;
+
+    assert!(resp
+        .body
+        .contains(r#"href="https://github.com/example/app/issues/2?tab=comments""#));
+    assert!(!resp.body.contains("utm_"));
+    assert!(!resp.body.contains("fbclid"));
+}
+
+#[tokio::test]
+async fn responses_carry_a_request_id() {
+    let app = test_app().await;
+    let resp = get(&app, "/").await;
+
+    assert!(resp.headers.contains_key("x-request-id"));
+}
+
+#[tokio::test]
+async fn api_returns_json_results_and_errors() {
+    let app = test_app().await;
+
+    let resp = get(&app, "/api/search?q=box+error").await;
+    assert_eq!(resp.status, StatusCode::OK);
+    let json: serde_json::Value = serde_json::from_str(&resp.body).unwrap();
+    assert_eq!(json["total_count"], 2);
+    assert_eq!(json["items"][0]["title"], "Box<dyn Error> loses backtrace");
+
+    let resp = get(
+        &app,
+        &format!("/api/search?q={}", Q_RATE_LIMITED.replace(' ', "+")),
+    )
+    .await;
+    assert_eq!(resp.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(resp.headers[header::RETRY_AFTER], "42");
+    let json: serde_json::Value = serde_json::from_str(&resp.body).unwrap();
+    assert_eq!(json["error"], "rate_limited");
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use axum::http::header;
+use common::*;
+
+#[tokio::test]
+async fn hardening_headers_are_set() {
+    let app = test_app().await;
+    let resp = get(&app, "/").await;
+
+    assert_eq!(resp.headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+    assert_eq!(resp.headers[header::REFERRER_POLICY], "no-referrer");
+    assert_eq!(resp.headers[header::X_FRAME_OPTIONS], "DENY");
+    let csp = resp.headers[header::CONTENT_SECURITY_POLICY]
+        .to_str()
+        .unwrap();
+    assert!(csp.contains("frame-ancestors 'none'"));
+    assert!(!csp.contains("unsafe-inline"));
+    assert!(!resp.headers.contains_key(header::STRICT_TRANSPORT_SECURITY));
+}
+
+#[tokio::test]
+async fn inline_scripts_carry_the_request_nonce() {
+    let app = test_app().await;
+    let resp = get(&app, &q("box error")).await;
+
+    let csp = resp.headers[header::CONTENT_SECURITY_POLICY]
+        .to_str()
+        .unwrap();
+    let start = csp.find("'nonce-").unwrap() + 7;
+    let nonce = &csp[start..start + csp[start..].find('\'').unwrap()];
+
+    assert!(resp.body.contains(&format!(r#"<script nonce="{nonce}">"#)));
+
+    let other = get(&app, &q("box error")).await;
+    assert!(!other.body.contains(nonce));
+}
+
+#[tokio::test]
+async fn hsts_only_behind_tls() {
+    let app = test_app().await;
+    let resp = get_with(&app, "/", &[("x-forwarded-proto", "https")]).await;
+
+    assert!(resp.headers.contains_key(header::STRICT_TRANSPORT_SECURITY));
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use std::path::PathBuf;
+
+use axum::http::{header, StatusCode};
+use common::*;
+
+const USER: (&str, &str) = ("x-forwarded-user", "alice");
+
+async fn proxy_app(data_dir: Option<PathBuf>) -> axum::Router {
+    test_app_with(|c| {
+        c.auth_proxy_header = Some("X-Forwarded-User".into());
+        c.data_dir = data_dir;
+    })
+    .await
+}
+
+async fn save(app: &axum::Router, form: &str) {
+    let resp = post_form(app, "/settings", form, &[USER]).await;
+    assert_eq!(resp.status, StatusCode::SEE_OTHER, "{}", resp.body);
+    assert_eq!(resp.headers[header::LOCATION], "/settings");
+}
+
+#[tokio::test]
+async fn anonymous_settings_live_in_a_cookie() {
+    let app = test_app().await;
+
+    let resp = get(&app, "/settings").await;
+    assert_eq!(resp.status, StatusCode::OK);
+    assert!(resp.body.contains("kept in a cookie in this browser"));
+    assert!(resp.body.contains(r#"value="github" checked"#));
+    assert!(!resp.body.contains("Provider tokens"));
+
+    let resp = post_form(
+        &app,
+        "/settings",
+        "provider=github&theme=light&per_page=50&token_github=ignored",
+        &[],
+    )
+    .await;
+    assert_eq!(resp.status, StatusCode::SEE_OTHER);
+    let set_cookie = resp.headers[header::SET_COOKIE].to_str().unwrap();
+    assert!(set_cookie.contains("HttpOnly"));
+    let prefs = cookie(&resp);
+
+    let results = get_with(&app, &q("box error"), &[("cookie", &prefs)]).await;
+    assert!(results.body.contains("theme-light"));
+    // The new-tab box was left unticked.
+    assert!(!results.body.contains(r#"target="_blank""#));
+    let settings = get_with(&app, "/settings", &[("cookie", &prefs)]).await;
+    assert!(settings.body.contains(r#"value="50" selected"#));
+
+    // Without the cookie, or with a forged one, the defaults apply.
+    let plain = get(&app, &q("box error")).await;
+    assert!(plain.body.contains("theme-dark"));
+    assert!(plain.body.contains(r#"target="_blank""#));
+    let (name, value) = prefs.split_once('=').unwrap();
+    let forged = format!("{name}=x{value}");
+    let resp = get_with(&app, &q("box error"), &[("cookie", &forged)]).await;
+    assert!(resp.body.contains("theme-dark"));
+}
+
+#[tokio::test]
+async fn reset_restores_defaults() {
+    let app = test_app().await;
+    let resp = post_form(&app, "/settings", "theme=light", &[]).await;
+    let prefs = cookie(&resp);
+
+    let resp = post_form(&app, "/settings/reset", "", &[("cookie", &prefs)]).await;
+    assert_eq!(resp.status, StatusCode::SEE_OTHER);
+    let removed = resp.headers[header::SET_COOKIE].to_str().unwrap();
+    assert!(removed.starts_with("my_search_prefs=;"));
+
+    let app = proxy_app(None).await;
+    save(&app, "provider=github&theme=light").await;
+    let resp = post_form(&app, "/settings/reset", "", &[USER]).await;
+    assert_eq!(resp.status, StatusCode::SEE_OTHER);
+    let settings = get_with(&app, "/settings", &[USER]).await;
+    assert!(settings.body.contains("theme-dark"));
+}
+
+#[tokio::test]
+async fn appearance_follows_the_profile() {
+    let app = proxy_app(None).await;
+
+    let before = get_with(&app, &q("box error"), &[USER]).await;
+    assert!(before.body.contains("theme-dark density-comfortable"));
+
+    save(&app, "provider=github&theme=light&density=compact").await;
+
+    let after = get_with(&app, &q("box error"), &[USER]).await;
+    assert!(after.body.contains("theme-light density-compact"));
+    let settings = get_with(&app, "/settings", &[USER]).await;
+    assert!(settings.body.contains(r#"value="light" checked"#));
+
+    // Other users keep the defaults.
+    let bob = get_with(&app, &q("box error"), &[("x-forwarded-user", "bob")]).await;
+    assert!(bob.body.contains("theme-dark density-comfortable"));
+}
+
+#[tokio::test]
+async fn bangs_override_disabled_providers() {
+    let app = proxy_app(None).await;
+    save(&app, "theme=dark").await;
+
+    let resp = get_with(&app, &q("box error"), &[USER]).await;
+    assert_eq!(resp.status, StatusCode::BAD_REQUEST);
+    assert!(resp.body.contains("turned off in your settings"));
+
+    let resp = get_with(&app, &q("!gh box error"), &[USER]).await;
+    assert_eq!(resp.status, StatusCode::OK);
+    assert!(resp.body.contains("Box&lt;dyn Error&gt; loses backtrace"));
+
+    save(&app, "default_bang=gh").await;
+    let resp = get_with(&app, &q("box error"), &[USER]).await;
+    assert_eq!(resp.status, StatusCode::OK);
+
+    let resp = get_with(&app, &q("!gh"), &[USER]).await;
+    assert_eq!(resp.status, StatusCode::BAD_REQUEST);
+    assert!(resp.body.contains("the search query is empty"));
+}
+
+#[tokio::test]
+async fn personal_tokens_are_sent_to_the_provider() {
+    let app = proxy_app(None).await;
+    save(
+        &app,
+        &format!("provider=github&token_github={REVOKED_TOKEN}"),
+    )
+    .await;
+
+    let resp = get_with(&app, &q("box error"), &[USER]).await;
+    assert_eq!(resp.status, StatusCode::BAD_GATEWAY);
+    assert!(resp.body.contains("Search Provider Rejected Credentials"));
+
+    let settings = get_with(&app, "/settings", &[USER]).await;
+    assert!(!settings.body.contains(REVOKED_TOKEN));
+    assert!(settings.body.contains(r#"placeholder="saved""#));
+
+    save(&app, "provider=github&clear_token=github").await;
+    let resp = get_with(&app, &q("box error"), &[USER]).await;
+    assert_eq!(resp.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn profiles_survive_a_restart() {
+    let dir = std::env::temp_dir().join(format!("my-search-settings-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let app = proxy_app(Some(dir.clone())).await;
+    save(&app, "provider=github&theme=light").await;
+    assert!(dir.join("profiles.json").exists());
+
+    let restarted = proxy_app(Some(dir.clone())).await;
+    let resp = get_with(&restarted, "/settings", &[USER]).await;
+    assert!(resp.body.contains("theme-light"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
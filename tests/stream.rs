@@ -0,0 +1,98 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use common::*;
+use my_search::config::Rate;
+
+/// `(event, data)` pairs of a complete event stream.
+fn events(body: &str) -> Vec<(String, String)> {
+    body.split("\n\n")
+        .filter(|block| !block.trim().is_empty())
+        .map(|block| {
+            let mut event = String::new();
+            let mut data = Vec::new();
+            for line in block.lines() {
+                if let Some(e) = line.strip_prefix("event: ") {
+                    event = e.into();
+                } else if let Some(d) = line.strip_prefix("data: ") {
+                    data.push(d);
+                }
+            }
+            (event, data.join("\n"))
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn results_arrive_as_fragments() {
+    let app = test_app().await;
+    let resp = get(&app, "/stream?q=box+error").await;
+    assert_eq!(resp.status, StatusCode::OK);
+    assert_eq!(resp.headers[header::CONTENT_TYPE], "text/event-stream");
+
+    let events = events(&resp.body);
+    let names: Vec<&str> = events.iter().map(|(e, _)| e.as_str()).collect();
+    assert_eq!(names, ["results", "done"]);
+    let fragment = &events[0].1;
+    assert!(fragment.starts_with(r#"<section class="space-y-4" data-provider="github">"#));
+    assert!(fragment.contains("2 results from github"));
+    assert!(fragment.contains("Box&lt;dyn Error&gt; loses backtrace"));
+    assert!(!fragment.contains("<html") && !fragment.contains("<head"));
+
+    let history = get(&app, "/history").await;
+    assert!(history.body.contains("box error"));
+}
+
+#[tokio::test]
+async fn provider_errors_are_events() {
+    let app = test_app().await;
+    let resp = get(&app, &format!("/stream?q={Q_INVALID}")).await;
+    assert_eq!(resp.status, StatusCode::OK);
+
+    let events = events(&resp.body);
+    assert_eq!(events[0].0, "failed");
+    assert!(events[0].1.contains("Invalid Search"));
+    assert_eq!(events[1].0, "done");
+}
+
+#[tokio::test]
+async fn streaming_page_is_a_shell() {
+    let app = test_app().await;
+    let resp = get(&app, "/?q=box+error&stream=true").await;
+    assert_eq!(resp.status, StatusCode::OK);
+    assert!(resp
+        .body
+        .contains(r#"data-stream="/stream?q=box+error" data-fallback="/?q=box+error""#));
+    assert!(resp.body.contains("Searching…"));
+    assert!(!resp.body.contains("loses backtrace"));
+
+    // Without the flag the page renders the results itself, as before.
+    let resp = get(&app, &q("box error")).await;
+    assert!(resp.body.contains("loses backtrace"));
+    assert!(!resp.body.contains("data-stream"));
+}
+
+#[tokio::test]
+async fn only_the_stream_counts_against_the_rate_limit() {
+    let app = test_app_with(|c| {
+        c.search_rate_limit = Some(Rate {
+            burst: 1,
+            per: Duration::from_secs(60),
+        })
+    })
+    .await;
+    for _ in 0..3 {
+        let resp = get(&app, "/?q=box+error&stream=true").await;
+        assert_eq!(resp.status, StatusCode::OK);
+    }
+    assert_eq!(
+        get(&app, "/stream?q=box+error").await.status,
+        StatusCode::OK
+    );
+    assert_eq!(
+        get(&app, "/stream?q=box+error").await.status,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use std::{fs, io, net::SocketAddr, path::Path, sync::Arc, time::Duration};
+
+use axum::http::{header, StatusCode};
+use common::*;
+use my_search::{
+    config::TlsConfig,
+    tls::{self, redirect_app},
+};
+use rcgen::CertifiedKey;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_rustls::{
+    rustls::{self, pki_types::ServerName, ClientConfig, RootCertStore},
+    TlsConnector,
+};
+
+fn self_signed() -> CertifiedKey {
+    rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap()
+}
+
+fn install(dir: &Path, cert: &CertifiedKey) {
+    fs::write(dir.join("key.pem"), cert.key_pair.serialize_pem()).unwrap();
+    fs::write(dir.join("cert.pem"), cert.cert.pem()).unwrap();
+}
+
+/// `GET /healthz` over TLS, trusting only `cert`.
+async fn healthz(addr: SocketAddr, cert: &CertifiedKey) -> io::Result<String> {
+    let mut roots = RootCertStore::empty();
+    roots.add(cert.cert.der().clone()).unwrap();
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+    let tcp = tokio::net::TcpStream::connect(addr).await?;
+    let mut stream = TlsConnector::from(Arc::new(config))
+        .connect(ServerName::try_from("localhost").unwrap(), tcp)
+        .await?;
+    stream
+        .write_all(b"GET /healthz HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .await?;
+    let mut resp = Vec::new();
+    // The server may close without a TLS close_notify; what arrived is enough.
+    let _ = stream.read_to_end(&mut resp).await;
+    Ok(String::from_utf8_lossy(&resp).into_owned())
+}
+
+#[tokio::test]
+async fn serves_https_and_picks_up_a_new_certificate() {
+    let dir = std::env::temp_dir().join(format!("my-search-tls-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let old = self_signed();
+    install(&dir, &old);
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = TlsConfig {
+        cert: dir.join("cert.pem"),
+        key: dir.join("key.pem"),
+        redirect_port: None,
+        reload_interval: Duration::from_millis(50),
+    };
+    let app = test_app().await;
+    tokio::spawn(async move { tls::serve(app, listener, &config).await });
+
+    let resp = healthz(addr, &old).await.unwrap();
+    assert!(resp.starts_with("HTTP/1.1 200 OK"), "{resp}");
+
+    let new = self_signed();
+    install(&dir, &new);
+    let mut reloaded = false;
+    for _ in 0..100 {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        if healthz(addr, &new).await.is_ok() {
+            reloaded = true;
+            break;
+        }
+    }
+    assert!(reloaded, "new certificate not served");
+    assert!(healthz(addr, &old).await.is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn http_redirects_to_https_port() {
+    let app = redirect_app(8443);
+    let resp = get_with(&app, "/?q=box+error", &[("host", "search.internal:8080")]).await;
+
+    assert_eq!(resp.status, StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(
+        resp.headers[header::LOCATION],
+        "https://search.internal:8443/?q=box+error"
+    );
+}
+
+#[tokio::test]
+async fn default_https_port_is_omitted() {
+    let app = redirect_app(443);
+    let resp = get_with(&app, "/status", &[("host", "search.internal")]).await;
+
+    assert_eq!(
+        resp.headers[header::LOCATION],
+        "https://search.internal/status"
+    );
+}
+
+#[tokio::test]
+async fn redirect_requires_host() {
+    let app = redirect_app(443);
+    let resp = get(&app, "/").await;
+
+    assert_eq!(resp.status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,328 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Redirect,
+    routing::post,
+    Router,
+};
+use common::*;
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+
+#[derive(Clone, Default)]
+struct Received(Arc<Mutex<Vec<(HeaderMap, String)>>>);
+
+impl Received {
+    fn all(&self) -> Vec<(HeaderMap, String)> {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// Deliveries run in the background, so wait for `n` requests to arrive.
+    async fn wait_for(&self, n: usize) -> Vec<(HeaderMap, String)> {
+        for _ in 0..200 {
+            if self.0.lock().unwrap().len() >= n {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        self.all()
+    }
+}
+
+/// Receiver that records every request. `/flaky` answers 503 to the first two
+/// calls, `/gone` always answers 410, `/moved` redirects to `/hook` and `/slow`
+/// takes a minute to answer.
+async fn receiver() -> (String, Received) {
+    async fn record(State(received): State<Received>, headers: HeaderMap, body: String) -> usize {
+        let mut all = received.0.lock().unwrap();
+        all.push((headers, body));
+        all.len()
+    }
+    let received = Received::default();
+    let app = Router::new()
+        .route(
+            "/hook",
+            post(|s, h, b| async move {
+                record(s, h, b).await;
+                StatusCode::NO_CONTENT
+            }),
+        )
+        .route(
+            "/flaky",
+            post(|s, h, b| async move {
+                match record(s, h, b).await {
+                    1 | 2 => StatusCode::SERVICE_UNAVAILABLE,
+                    _ => StatusCode::OK,
+                }
+            }),
+        )
+        .route(
+            "/gone",
+            post(|s, h, b| async move {
+                record(s, h, b).await;
+                StatusCode::GONE
+            }),
+        )
+        .route("/moved", post(|| async { Redirect::temporary("/hook") }))
+        .route(
+            "/slow",
+            post(|s, h, b| async move {
+                record(s, h, b).await;
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                StatusCode::OK
+            }),
+        )
+        .with_state(received.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (url, received)
+}
+
+/// The receivers run on loopback, which webhooks may only reach when allowed.
+async fn webhook_app() -> axum::Router {
+    test_app_with(|c| {
+        c.webhook_retry_delay = Duration::from_millis(10);
+        c.webhook_allow_private = true;
+    })
+    .await
+}
+
+/// The dashboard once the delivery log has an entry.
+async fn logged(app: &axum::Router) -> String {
+    for _ in 0..200 {
+        let page = get(app, "/saved").await;
+        if page.body.contains(" attempt") {
+            return page.body;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("no delivery logged");
+}
+
+fn hook(url: &str, format: &str) -> String {
+    let url = url::form_urlencoded::byte_serialize(url.as_bytes()).collect::<String>();
+    format!("url={url}&format={format}")
+}
+
+/// Watches `Q_GROWING` with a webhook and runs it again so it finds new items.
+async fn watch_and_grow(app: &axum::Router, hook: &str) {
+    post_form(app, "/saved", &format!("query={Q_GROWING}"), &[]).await;
+    let resp = post_form(app, "/saved/0/webhook", hook, &[]).await;
+    assert_eq!(resp.status, StatusCode::SEE_OTHER);
+    post_form(app, "/saved/0/run", "", &[]).await;
+}
+
+#[tokio::test]
+async fn new_results_are_posted_signed() {
+    let (url, received) = receiver().await;
+    let app = webhook_app().await;
+    let form = hook(&format!("{url}/hook"), "generic") + "&secret=s3cret";
+    watch_and_grow(&app, &form).await;
+
+    let all = received.wait_for(1).await;
+    assert_eq!(all.len(), 1);
+    let (headers, body) = &all[0];
+    assert_eq!(headers["content-type"], "application/json");
+    assert_eq!(headers["x-my-search-event"], "new_results");
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").unwrap();
+    mac.update(body.as_bytes());
+    let expected = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+    assert_eq!(headers["x-my-search-signature-256"], expected.as_str());
+
+    let json: Value = serde_json::from_str(body).unwrap();
+    assert_eq!(json["event"], "new_results");
+    assert_eq!(json["saved_search"]["query"], Q_GROWING);
+    assert_eq!(json["items"].as_array().unwrap().len(), 2);
+
+    let page = logged(&app).await;
+    assert!(page.contains("Delivered"));
+    assert!(page.contains("2 items · 1 attempt · HTTP 204"));
+
+    // Nothing new, nothing posted.
+    post_form(&app, "/saved/0/run", "", &[]).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(received.all().len(), 1);
+}
+
+#[tokio::test]
+async fn slack_and_matrix_payloads() {
+    let (url, received) = receiver().await;
+
+    let app = webhook_app().await;
+    watch_and_grow(&app, &hook(&format!("{url}/hook"), "slack")).await;
+    received.wait_for(1).await;
+    let app = webhook_app().await;
+    watch_and_grow(&app, &hook(&format!("{url}/hook"), "matrix")).await;
+
+    let all = received.wait_for(2).await;
+    assert_eq!(all.len(), 2);
+    assert!(!all[0].0.contains_key("x-my-search-signature-256"));
+
+    let slack: Value = serde_json::from_str(&all[0].1).unwrap();
+    let text = slack["text"].as_str().unwrap();
+    assert!(text.starts_with("2 new results for \"growing\""));
+    assert!(text.contains("|Box&lt;dyn Error&gt; loses backtrace>"));
+
+    let matrix: Value = serde_json::from_str(&all[1].1).unwrap();
+    assert!(matrix["text"]
+        .as_str()
+        .unwrap()
+        .contains("- Box<dyn Error> loses backtrace"));
+    assert!(matrix["html"]
+        .as_str()
+        .unwrap()
+        .contains(">Box&lt;dyn Error&gt; loses backtrace</a>"));
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_retried_and_logged() {
+    let (url, received) = receiver().await;
+
+    let app = webhook_app().await;
+    watch_and_grow(&app, &hook(&format!("{url}/flaky"), "generic")).await;
+    let page = logged(&app).await;
+    assert_eq!(received.all().len(), 3);
+    assert!(page.contains("2 items · 3 attempts · HTTP 200"));
+
+    // A 4xx other than 429 is not worth retrying.
+    let app = webhook_app().await;
+    watch_and_grow(&app, &hook(&format!("{url}/gone"), "generic")).await;
+    let page = logged(&app).await;
+    assert_eq!(received.all().len(), 4);
+    assert!(page.contains("Failed"));
+    assert!(page.contains("receiver answered 410 Gone"));
+
+    // Redirects aren't followed, since they could point anywhere.
+    let app = webhook_app().await;
+    watch_and_grow(&app, &hook(&format!("{url}/moved"), "generic")).await;
+    let page = logged(&app).await;
+    assert!(page.contains("1 attempt · HTTP 307"));
+    assert_eq!(received.all().len(), 4);
+}
+
+#[tokio::test]
+async fn private_receivers_are_refused_by_default() {
+    let app = test_app().await;
+    post_form(&app, "/saved", "query=anything", &[]).await;
+
+    for url in [
+        "http://127.0.0.1:8080/hook",
+        "http://localhost/hook",
+        "http://10.0.0.7/hook",
+        "http://192.168.1.1/hook",
+        "http://169.254.169.254/latest/meta-data",
+        "http://[::1]/hook",
+        "http://[::ffff:127.0.0.1]/hook",
+        "http://[fd00::1]/hook",
+        "http://[64:ff9b::7f00:1]/hook",
+        "http://[2002:7f00:1::1]/hook",
+        "http://224.0.0.251/hook",
+        "http://[ff02::1]/hook",
+    ] {
+        let resp = post_form(&app, "/saved/0/webhook", &hook(url, "generic"), &[]).await;
+        assert_eq!(resp.status, StatusCode::BAD_REQUEST, "{url}");
+    }
+
+    let resp = post_form(
+        &app,
+        "/saved/0/webhook",
+        &hook("https://hooks.example.com/hook", "generic"),
+        &[],
+    )
+    .await;
+    assert_eq!(resp.status, StatusCode::SEE_OTHER);
+}
+
+#[tokio::test]
+async fn slow_receivers_do_not_hold_up_the_page() {
+    let (url, _) = receiver().await;
+    let app = webhook_app().await;
+    post_form(&app, "/saved", &format!("query={Q_GROWING}"), &[]).await;
+    post_form(
+        &app,
+        "/saved/0/webhook",
+        &hook(&format!("{url}/slow"), "generic"),
+        &[],
+    )
+    .await;
+
+    for uri in ["/saved/0/run", "/saved/0/webhook/test"] {
+        let resp = tokio::time::timeout(Duration::from_secs(5), post_form(&app, uri, "", &[]))
+            .await
+            .expect("redirected without waiting for the receiver");
+        assert_eq!(resp.status, StatusCode::SEE_OTHER);
+    }
+}
+
+#[tokio::test]
+async fn one_test_delivery_at_a_time() {
+    let (url, received) = receiver().await;
+    let app = webhook_app().await;
+    post_form(&app, "/saved", "query=anything", &[]).await;
+    post_form(
+        &app,
+        "/saved/0/webhook",
+        &hook(&format!("{url}/slow"), "generic"),
+        &[],
+    )
+    .await;
+
+    for _ in 0..3 {
+        let resp = post_form(&app, "/saved/0/webhook/test", "", &[]).await;
+        assert_eq!(resp.status, StatusCode::SEE_OTHER);
+    }
+    received.wait_for(1).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(received.all().len(), 1);
+}
+
+#[tokio::test]
+async fn webhook_urls_are_validated() {
+    let app = webhook_app().await;
+    post_form(&app, "/saved", "query=anything", &[]).await;
+
+    let resp = post_form(
+        &app,
+        "/saved/0/webhook",
+        "url=file%3A%2F%2F%2Fetc%2Fpasswd&format=generic",
+        &[],
+    )
+    .await;
+    assert_eq!(resp.status, StatusCode::BAD_REQUEST);
+    let resp = post_form(
+        &app,
+        "/saved/0/webhook",
+        "url=https%3A%2F%2Fexample.com&format=teams",
+        &[],
+    )
+    .await;
+    assert_eq!(resp.status, StatusCode::BAD_REQUEST);
+
+    // An empty URL removes the webhook.
+    post_form(
+        &app,
+        "/saved/0/webhook",
+        "url=https%3A%2F%2Fexample.com%2Fhook&format=slack",
+        &[],
+    )
+    .await;
+    assert!(get(&app, "/saved")
+        .await
+        .body
+        .contains("slack · https://example.com/hook"));
+    post_form(&app, "/saved/0/webhook", "url=&format=slack", &[]).await;
+    assert!(!get(&app, "/saved")
+        .await
+        .body
+        .contains("https://example.com/hook"));
+}